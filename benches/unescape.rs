@@ -0,0 +1,73 @@
+//! Timings of the fast paths against the plain per-char unescaping. Run with
+//! `cargo bench`, which prints the median time of each for a few inputs.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use escape_from_rust::{Mode, unescape_str, unescape_str_chunks, validate};
+
+fn main() {
+    let inputs = inputs();
+    println!("unescape_str_chunks vs unescape_str:");
+    for (name, text) in inputs.iter() {
+        let unescape = median_time(|| {
+            unescape_str(black_box(text), &mut |_, c| {
+                let _ = black_box(c);
+            })
+        });
+        let chunks = median_time(|| {
+            unescape_str_chunks(black_box(text), &mut |_, c| {
+                let _ = black_box(c);
+            })
+        });
+        report(name, "unescape_str", unescape, "unescape_str_chunks", chunks);
+    }
+
+    println!("validate vs unescape_str with a no-op callback:");
+    for (name, text) in inputs.iter() {
+        let unescape = median_time(|| unescape_str(black_box(text), &mut |_, _| ()));
+        let validate = median_time(|| {
+            black_box(validate(black_box(text), Mode::Str)).unwrap();
+        });
+        report(name, "unescape_str", unescape, "validate", validate);
+    }
+}
+
+/// Large literals of the kinds which are common in real code.
+fn inputs() -> Vec<(&'static str, String)> {
+    let sql = "SELECT id, name FROM users WHERE name LIKE 'a%' ORDER BY id;\n";
+    let prose = "Привет, мир! Hello, world! ";
+    let escapes = r"\t\u{1F63b}\x41\\\n";
+    vec![
+        ("ascii", sql.repeat(2000)),
+        ("unicode", prose.repeat(2000)),
+        ("escapes", escapes.repeat(2000)),
+    ]
+}
+
+fn median_time(mut f: impl FnMut()) -> Duration {
+    const ITERATIONS: u32 = 20;
+    let mut times: Vec<Duration> = (0..25)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                f();
+            }
+            start.elapsed() / ITERATIONS
+        })
+        .collect();
+    times.sort();
+    times[times.len() / 2]
+}
+
+fn report(input: &str, base_name: &str, base: Duration, fast_name: &str, fast: Duration) {
+    println!(
+        "  {:<8} {}: {:>10.2?}  {}: {:>10.2?}  {:.1}x",
+        input,
+        base_name,
+        base,
+        fast_name,
+        fast,
+        base.as_secs_f64() / fast.as_secs_f64()
+    );
+}
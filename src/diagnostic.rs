@@ -0,0 +1,168 @@
+//! A single representation of everything reported about a literal, errors
+//! and warnings alike, ready to be shown to the user.
+
+use std::ops::Range;
+
+use crate::{
+    EscapeError, EscapeWarning, Help, Mode, Severity, line_continuation_warnings,
+    unescape_char_or_byte, validate_all, validate_raw_all,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    Error(EscapeError),
+    Warning(EscapeWarning),
+}
+
+/// Ranges are relative to the contents of the literal (without quotes), the
+/// same as the ones passed to `to_diagnostic`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub message: String,
+    /// The range to point at.
+    pub primary_range: Range<usize>,
+    /// Other ranges to point at, with a label for each of them.
+    pub labels: Vec<(Range<usize>, String)>,
+    pub help: Option<Help>,
+}
+
+impl EscapeError {
+    /// Turns this error, reported at `range` of `literal_text` by one of the
+    /// `unescape_*` functions in `mode`, into a diagnostic.
+    pub fn to_diagnostic(&self, literal_text: &str, range: Range<usize>, mode: Mode) -> Diagnostic {
+        let primary_range = self.primary_range(literal_text, range.clone(), mode);
+        let labels = match &primary_range {
+            Some(primary_range) if *primary_range != range => {
+                let label = match self {
+                    EscapeError::MoreThanOneChar => "in this literal",
+                    _ => "in this escape",
+                };
+                vec![(range.clone(), label.to_string())]
+            }
+            _ => Vec::new(),
+        };
+        Diagnostic {
+            severity: self.severity(),
+            kind: DiagnosticKind::Error(*self),
+            message: self.to_string(),
+            primary_range: primary_range.unwrap_or_else(|| range.clone()),
+            labels,
+            help: self.help(literal_text, range, mode),
+        }
+    }
+}
+
+impl EscapeWarning {
+    /// Turns this warning, reported at `range` by `line_continuation_warnings`,
+    /// into a diagnostic.
+    pub fn to_diagnostic(&self, range: Range<usize>) -> Diagnostic {
+        Diagnostic {
+            severity: self.severity(),
+            kind: DiagnosticKind::Warning(*self),
+            message: self.to_string(),
+            primary_range: range,
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+}
+
+/// Returns all errors and warnings in a contents of a literal (without
+/// quotes) in `mode`, ordered by their position.
+pub fn diagnostics(literal_text: &str, mode: Mode) -> Vec<Diagnostic> {
+    if mode.in_single_quotes() {
+        return match unescape_char_or_byte(literal_text, mode) {
+            Ok(_) => Vec::new(),
+            Err(err) => vec![err.error.to_diagnostic(literal_text, err.range, mode)],
+        };
+    }
+
+    let errors = validate_all(literal_text, mode).err().unwrap_or_default();
+    let mut diagnostics: Vec<Diagnostic> = errors
+        .into_iter()
+        .map(|(range, err)| err.to_diagnostic(literal_text, range, mode))
+        .collect();
+    diagnostics.extend(
+        line_continuation_warnings(literal_text, mode)
+            .into_iter()
+            .map(|(range, warning)| warning.to_diagnostic(range)),
+    );
+    diagnostics.sort_by_key(|it| it.primary_range.start);
+    diagnostics
+}
+
+/// Like `diagnostics`, but for a contents of a raw string literal (without
+/// quotes and hashes).
+pub fn raw_diagnostics(literal_text: &str, mode: Mode) -> Vec<Diagnostic> {
+    let errors = validate_raw_all(literal_text, mode).err().unwrap_or_default();
+    errors.into_iter().map(|(range, err)| err.to_diagnostic(literal_text, range, mode)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let literal_text = "a\\q\\\n\n\u{a0}\\u{D800}";
+        let actual = diagnostics(literal_text, Mode::Str);
+        let summary: Vec<_> =
+            actual.iter().map(|it| (it.severity, it.kind, it.primary_range.clone())).collect();
+        assert_eq!(
+            summary,
+            [
+                (Severity::Error, DiagnosticKind::Error(EscapeError::InvalidEscape), 2..3),
+                (
+                    Severity::Warning,
+                    DiagnosticKind::Warning(EscapeWarning::MultipleSkippedLines),
+                    4..6
+                ),
+                (
+                    Severity::Warning,
+                    DiagnosticKind::Warning(EscapeWarning::UnskippedWhitespace('\u{a0}')),
+                    6..8
+                ),
+                (
+                    Severity::Error,
+                    DiagnosticKind::Error(EscapeError::LoneSurrogateUnicodeEscape),
+                    11..15
+                ),
+            ]
+        );
+
+        let invalid_escape = &actual[0];
+        assert_eq!(invalid_escape.message, "unknown character escape");
+        assert_eq!(invalid_escape.labels, [(1..3, "in this escape".to_string())]);
+        let help = invalid_escape.help.as_ref().unwrap();
+        assert_eq!(help.suggestion, Some((1..2, r"\\".to_string())));
+
+        let whitespace = &actual[2];
+        assert_eq!(whitespace.message, "whitespace symbol (U+00A0) is not skipped");
+        assert_eq!(whitespace.help, None);
+    }
+
+    #[test]
+    fn test_char_and_raw_diagnostics() {
+        assert_eq!(diagnostics("a", Mode::Char), []);
+        let actual = diagnostics(r"\na", Mode::Char);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].kind, DiagnosticKind::Error(EscapeError::MoreThanOneChar));
+        assert_eq!(actual[0].primary_range, 2..3);
+        assert_eq!(actual[0].labels, [(0..3, "in this literal".to_string())]);
+
+        let actual = diagnostics(r"\xffa", Mode::Byte);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].primary_range, 4..5);
+
+        let actual = raw_diagnostics("a\rb\\q", Mode::Str);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(
+            actual[0].kind,
+            DiagnosticKind::Error(EscapeError::BareCarriageReturnInRawString)
+        );
+        assert_eq!(actual[0].primary_range, 1..2);
+        assert_eq!(actual[0].labels, []);
+    }
+}
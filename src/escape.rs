@@ -0,0 +1,303 @@
+//! The inverse direction: turning values into contents of literals which
+//! unescape back to exactly these values.
+
+use std::fmt::Write;
+
+use crate::{escape_simple, Mode};
+
+/// Controls which characters get escaped when rendering values as literals.
+/// The default escapes control and non-printable characters, and keeps
+/// everything else as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EscapeOptions {
+    /// Escape only what can't appear in the literal unescaped: backslash, the
+    /// quote of the literal, and, in single quotes, newline and tab.
+    pub only_required: bool,
+    /// Escape all non-ASCII characters, printable or not, as `\u{..}`.
+    pub escape_non_ascii: bool,
+    /// Use `\xNN` rather than `\u{..}` for ASCII control characters.
+    pub prefer_hex_escapes: bool,
+    /// Use uppercase hex digits in `\xNN` and `\u{..}` escapes.
+    pub uppercase_hex: bool,
+}
+
+/// Produces a contents of a char or string literal (without quotes) which
+/// unescapes to `c`.
+pub fn escape_char(c: char, mode: Mode) -> String {
+    escape_char_with(c, mode, &EscapeOptions::default())
+}
+
+pub fn escape_char_with(c: char, mode: Mode, options: &EscapeOptions) -> String {
+    assert!(!mode.is_bytes() && mode != Mode::CStr);
+    let mut buf = String::new();
+    escape_char_into(c, mode, options, &mut buf);
+    buf
+}
+
+/// Produces a contents of a string literal (without quotes) which unescapes
+/// to `text`. C strings are escaped with `escape_byte_str`.
+pub fn escape_str(text: &str, mode: Mode) -> String {
+    escape_str_with(text, mode, &EscapeOptions::default())
+}
+
+pub fn escape_str_with(text: &str, mode: Mode, options: &EscapeOptions) -> String {
+    assert!(!mode.is_bytes() && mode != Mode::CStr);
+    let mut buf = String::with_capacity(text.len());
+    text.chars().for_each(|c| escape_char_into(c, mode, options, &mut buf));
+    buf
+}
+
+/// Produces a contents of a byte literal (without quotes) which unescapes to
+/// `byte`.
+pub fn escape_byte(byte: u8, mode: Mode) -> String {
+    escape_byte_with(byte, mode, &EscapeOptions::default())
+}
+
+pub fn escape_byte_with(byte: u8, mode: Mode, options: &EscapeOptions) -> String {
+    assert!(mode.is_bytes());
+    let mut buf = String::new();
+    escape_char_into(byte as char, mode, options, &mut buf);
+    buf
+}
+
+/// Produces a contents of a byte or C string literal (without quotes) which
+/// unescapes to `bytes`. In `Mode::CStr`, valid UTF-8 is kept as characters.
+///
+/// Returns `None` for a C string with a NUL, which no literal can express.
+pub fn escape_byte_str(bytes: &[u8], mode: Mode) -> Option<String> {
+    escape_byte_str_with(bytes, mode, &EscapeOptions::default())
+}
+
+pub fn escape_byte_str_with(bytes: &[u8], mode: Mode, options: &EscapeOptions) -> Option<String> {
+    assert!(mode == Mode::ByteStr || mode == Mode::CStr);
+    let mut buf = String::with_capacity(bytes.len());
+    if mode == Mode::CStr {
+        if bytes.contains(&0) {
+            return None;
+        }
+        escape_c_str_into(bytes, options, &mut buf);
+    } else {
+        bytes.iter().for_each(|&b| escape_char_into(b as char, mode, options, &mut buf));
+    }
+    Some(buf)
+}
+
+/// Escapes runs of valid UTF-8 as characters and everything else as bytes.
+/// `bytes` must not contain NUL.
+fn escape_c_str_into(bytes: &[u8], options: &EscapeOptions, buf: &mut String) {
+    let mut rest = bytes;
+    loop {
+        let err = match std::str::from_utf8(rest) {
+            Ok(text) => {
+                text.chars().for_each(|c| escape_char_into(c, Mode::CStr, options, buf));
+                return;
+            }
+            Err(err) => err,
+        };
+        let (valid, invalid) = rest.split_at(err.valid_up_to());
+        let valid = std::str::from_utf8(valid).expect("checked by from_utf8 above");
+        valid.chars().for_each(|c| escape_char_into(c, Mode::CStr, options, buf));
+        let invalid_len = err.error_len().unwrap_or(invalid.len());
+        for &b in &invalid[..invalid_len] {
+            escape_char_into(b as char, Mode::ByteStr, options, buf);
+        }
+        rest = &invalid[invalid_len..];
+    }
+}
+
+/// In byte modes, `c` is a byte value, so everything outside of ASCII must be
+/// escaped as `\xNN`.
+fn escape_char_into(c: char, mode: Mode, options: &EscapeOptions, buf: &mut String) {
+    let is_required = match c {
+        '\\' | '\r' => true,
+        '\'' => mode.in_single_quotes(),
+        '"' => mode.in_double_quotes(),
+        '\n' | '\t' => mode.in_single_quotes(),
+        _ => false,
+    };
+    let is_simple = matches!(c, '\n' | '\t' | '\r' | '\0');
+    if is_required || (is_simple && !options.only_required) {
+        let e = escape_simple(c).expect("all of these have simple escapes");
+        buf.push('\\');
+        buf.push(e);
+        return;
+    }
+
+    let escape = if mode.is_bytes() && !c.is_ascii() {
+        Some(HexEscape::X)
+    } else if c.is_ascii_control() && !options.only_required {
+        Some(if options.prefer_hex_escapes || mode.is_bytes() {
+            HexEscape::X
+        } else {
+            HexEscape::U
+        })
+    } else if !c.is_ascii()
+        && (options.escape_non_ascii || (!options.only_required && !is_printable(c)))
+    {
+        Some(HexEscape::U)
+    } else {
+        None
+    };
+
+    let value = c as u32;
+    match (escape, options.uppercase_hex) {
+        (None, _) => buf.push(c),
+        (Some(HexEscape::X), false) => write!(buf, "\\x{:02x}", value).unwrap(),
+        (Some(HexEscape::X), true) => write!(buf, "\\x{:02X}", value).unwrap(),
+        (Some(HexEscape::U), false) => write!(buf, "\\u{{{:x}}}", value).unwrap(),
+        (Some(HexEscape::U), true) => write!(buf, "\\u{{{:X}}}", value).unwrap(),
+    }
+}
+
+enum HexEscape {
+    X,
+    U,
+}
+
+fn is_printable(c: char) -> bool {
+    c.escape_debug().len() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unescape_byte, unescape_byte_str, unescape_c_str, unescape_char, unescape_str};
+
+    #[test]
+    fn test_escape_char() {
+        fn check(c: char, expected: &str) {
+            let actual = escape_char(c, Mode::Char);
+            assert_eq!(actual, expected);
+            assert_eq!(unescape_char(&actual), Ok(c));
+        }
+
+        check('a', "a");
+        check('"', "\"");
+        check('\'', r"\'");
+        check('\\', r"\\");
+        check('\n', r"\n");
+        check('\t', r"\t");
+        check('\r', r"\r");
+        check('\0', r"\0");
+        check('\x1b', r"\u{1b}");
+        check('\x7f', r"\u{7f}");
+        check('ы', "ы");
+        check('🦀', "🦀");
+        check('\u{200b}', r"\u{200b}");
+        check('\u{301}', r"\u{301}");
+    }
+
+    #[test]
+    fn test_escape_str() {
+        fn check(text: &str, expected: &str) {
+            let actual = escape_str(text, Mode::Str);
+            assert_eq!(actual, expected);
+
+            let mut unescaped = String::new();
+            unescape_str(&actual, &mut |_, c| unescaped.push(c.unwrap()));
+            assert_eq!(unescaped, text);
+        }
+
+        check("", "");
+        check("hello world", "hello world");
+        check("thread's", "thread's");
+        check("\"quoted\"", r#"\"quoted\""#);
+        check("a\\b", r"a\\b");
+        check("one\ntwo\tthree\r", r"one\ntwo\tthree\r");
+        check("hello \\\n     world", r"hello \\\n     world");
+        check("Привет, 🦀", "Привет, 🦀");
+    }
+
+    #[test]
+    fn test_escape_byte() {
+        fn check(byte: u8, expected: &str) {
+            let actual = escape_byte(byte, Mode::Byte);
+            assert_eq!(actual, expected);
+            assert_eq!(unescape_byte(&actual), Ok(byte));
+        }
+
+        check(b'a', "a");
+        check(b'"', "\"");
+        check(b'\'', r"\'");
+        check(b'\n', r"\n");
+        check(b'\0', r"\0");
+        check(0x1b, r"\x1b");
+        check(0x7f, r"\x7f");
+        check(0x80, r"\x80");
+        check(0xff, r"\xff");
+    }
+
+    #[test]
+    fn test_escape_byte_str() {
+        fn check(bytes: &[u8], expected: &str) {
+            let actual = escape_byte_str(bytes, Mode::ByteStr).unwrap();
+            assert_eq!(actual, expected);
+
+            let mut unescaped = Vec::new();
+            unescape_byte_str(&actual, &mut |_, b| unescaped.push(b.unwrap()));
+            assert_eq!(unescaped, bytes);
+        }
+
+        check(b"", "");
+        check(b"foo", "foo");
+        check(b"thread's", "thread's");
+        check(b"\"\\\n", r#"\"\\\n"#);
+        check(&[0, 1, 0x7f, 0x80, 0xff], r"\0\x01\x7f\x80\xff");
+        check("ы".as_bytes(), r"\xd1\x8b");
+    }
+
+    #[test]
+    fn test_escape_options() {
+        fn check(text: &str, mode: Mode, options: EscapeOptions, expected: &str) {
+            let actual = escape_str_with(text, mode, &options);
+            assert_eq!(actual, expected);
+
+            let mut unescaped = String::new();
+            unescape_str(&actual, &mut |_, c| unescaped.push(c.unwrap()));
+            assert_eq!(unescaped, text);
+        }
+
+        let text = "'\"\\\n\t\0\x1b ы\u{200b}";
+        let default = EscapeOptions::default();
+        check(text, Mode::Str, default, r#"'\"\\\n\t\0\u{1b} ы\u{200b}"#);
+        let options = EscapeOptions { only_required: true, ..default };
+        check(text, Mode::Str, options, "'\\\"\\\\\n\t\0\x1b ы\u{200b}");
+        let options = EscapeOptions { escape_non_ascii: true, ..default };
+        check(text, Mode::Str, options, r#"'\"\\\n\t\0\u{1b} \u{44b}\u{200b}"#);
+        let options = EscapeOptions { only_required: true, escape_non_ascii: true, ..default };
+        check(text, Mode::Str, options, "'\\\"\\\\\n\t\0\x1b \\u{44b}\\u{200b}");
+        let options = EscapeOptions { prefer_hex_escapes: true, ..default };
+        check(text, Mode::Str, options, r#"'\"\\\n\t\0\x1b ы\u{200b}"#);
+        let options = EscapeOptions { uppercase_hex: true, ..default };
+        check(text, Mode::Str, options, r#"'\"\\\n\t\0\u{1B} ы\u{200B}"#);
+
+        let options = EscapeOptions { only_required: true, ..default };
+        assert_eq!(escape_char_with('\n', Mode::Char, &options), r"\n");
+        assert_eq!(escape_char_with('"', Mode::Char, &options), "\"");
+        assert_eq!(escape_str_with("a\r\nb", Mode::Str, &options), "a\\r\nb");
+        assert_eq!(escape_byte_str_with(b"\t\xff", Mode::ByteStr, &options).unwrap(), "\t\\xff");
+        let options = EscapeOptions { uppercase_hex: true, ..default };
+        assert_eq!(escape_byte_with(0xab, Mode::Byte, &options), r"\xAB");
+    }
+
+    #[test]
+    fn test_escape_c_str() {
+        fn check(bytes: &[u8], expected: &str) {
+            let actual = escape_byte_str(bytes, Mode::CStr).unwrap();
+            assert_eq!(actual, expected);
+
+            let mut unescaped = Vec::new();
+            unescape_c_str(&actual, &mut |_, unit| unit.unwrap().push_to(&mut unescaped));
+            assert_eq!(unescaped, bytes);
+        }
+
+        check(b"", "");
+        check(b"a\"b\\\n", r#"a\"b\\\n"#);
+        check("ы\x1b".as_bytes(), r"ы\u{1b}");
+        check(b"\xd1\x8b\xd1", r"ы\xd1");
+        check(b"\xffa", r"\xffa");
+
+        assert_eq!(escape_byte_str(b"a\0", Mode::CStr), None);
+        assert_eq!(escape_byte_str(b"a\0", Mode::ByteStr).as_deref(), Some(r"a\0"));
+    }
+}
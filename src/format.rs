@@ -0,0 +1,491 @@
+//! Parsing of `format_args!` format strings. The string is unescaped first,
+//! like the compiler does, so `\u{7b}` is a brace as well, and ranges are
+//! mapped back to the literal with a `SourceMap`.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{EscapeError, SourceMap, unescape_str_mapped};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatPiece {
+    pub range: Range<usize>,
+    pub kind: FormatPieceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatPieceKind {
+    /// Text with `{{` and `}}` replaced by single braces.
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// `{argument:spec}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub argument: FormatArgument,
+    pub argument_range: Range<usize>,
+    pub spec: FormatSpec,
+    /// The range of the spec after the colon, empty if there's no colon.
+    pub spec_range: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatArgument {
+    /// `{}`, the next positional argument.
+    Next,
+    /// `{0}`.
+    Index(usize),
+    /// `{name}`, either an explicitly named argument or an implicitly
+    /// captured variable.
+    Name(String),
+}
+
+/// `[[fill]align][sign]['#']['0'][width]['.' precision]type`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub fill: Option<char>,
+    pub align: Option<FormatAlign>,
+    pub sign: Option<FormatSign>,
+    pub alternate: bool,
+    pub zero_pad: bool,
+    pub width: Option<FormatCount>,
+    pub precision: Option<FormatCount>,
+    /// The formatting trait, like `""`, `"?"`, `"x"` or `"x?"`.
+    pub ty: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatSign {
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatCount {
+    /// `8`.
+    Is(usize),
+    /// `0$` or `name$`.
+    Argument(FormatArgument),
+    /// `.*`, which takes the precision from the next positional argument.
+    Star,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatError {
+    UnmatchedOpenBrace,
+    UnmatchedCloseBrace,
+    InvalidArgument,
+    InvalidSpec,
+    Escape(EscapeError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            FormatError::UnmatchedOpenBrace => {
+                "invalid format string: expected `}` but string was terminated"
+            }
+            FormatError::UnmatchedCloseBrace => "invalid format string: unmatched `}` found",
+            FormatError::InvalidArgument => "invalid format string: invalid argument name",
+            FormatError::InvalidSpec => "invalid format string: invalid format spec",
+            FormatError::Escape(err) => return err.fmt(f),
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FormatError::Escape(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Takes a contents of a string literal (without quotes) which is used as a
+/// format string, and splits it into pieces, or returns all errors in it.
+/// Ranges are relative to `literal_text`.
+pub fn parse_format_str(
+    literal_text: &str,
+) -> Result<Vec<FormatPiece>, Vec<(Range<usize>, FormatError)>> {
+    let (value, map) = unescape_str_mapped(literal_text).map_err(|errors| {
+        errors.into_iter().map(|(range, err)| (range, FormatError::Escape(err))).collect::<Vec<_>>()
+    })?;
+
+    let mut parser = Parser { value: &value, map: &map, pieces: Vec::new(), errors: Vec::new() };
+    parser.parse();
+    if !parser.errors.is_empty() {
+        return Err(parser.errors);
+    }
+    Ok(parser.pieces)
+}
+
+/// Works with offsets in the unescaped value, and maps them to the literal
+/// when reporting pieces and errors.
+struct Parser<'a> {
+    value: &'a str,
+    map: &'a SourceMap,
+    pieces: Vec<FormatPiece>,
+    errors: Vec<(Range<usize>, FormatError)>,
+}
+
+impl Parser<'_> {
+    fn parse(&mut self) {
+        let mut text = String::new();
+        let mut text_start = 0;
+        let mut pos = 0;
+        while let Some(brace) = self.value[pos..].find(&['{', '}'][..]) {
+            let brace = pos + brace;
+            text.push_str(&self.value[pos..brace]);
+            let rest = &self.value[brace..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push_str(&rest[..1]);
+                pos = brace + 2;
+                continue;
+            }
+            if rest.starts_with('}') {
+                self.error(brace..brace + 1, FormatError::UnmatchedCloseBrace);
+                pos = brace + 1;
+                continue;
+            }
+
+            self.push_text(&mut text, text_start..brace);
+            let close = match rest.find('}') {
+                Some(close) => brace + close,
+                None => {
+                    self.error(brace..brace + 1, FormatError::UnmatchedOpenBrace);
+                    return;
+                }
+            };
+            if let Some(placeholder) = self.parse_placeholder(brace + 1..close) {
+                let range = self.map.to_source_range(brace..close + 1);
+                self.pieces
+                    .push(FormatPiece { range, kind: FormatPieceKind::Placeholder(placeholder) });
+            }
+            pos = close + 1;
+            text_start = pos;
+        }
+        text.push_str(&self.value[pos..]);
+        self.push_text(&mut text, text_start..self.value.len());
+    }
+
+    fn push_text(&mut self, text: &mut String, range: Range<usize>) {
+        if text.is_empty() {
+            return;
+        }
+        let range = self.map.to_source_range(range);
+        self.pieces.push(FormatPiece { range, kind: FormatPieceKind::Text(std::mem::take(text)) });
+    }
+
+    fn error(&mut self, range: Range<usize>, err: FormatError) {
+        let range = self.map.to_source_range(range);
+        self.errors.push((range, err));
+    }
+
+    /// Parses the text between the braces at `inner` range of the value.
+    fn parse_placeholder(&mut self, inner: Range<usize>) -> Option<Placeholder> {
+        let text = &self.value[inner.clone()];
+        let (argument_len, spec_start) = match text.find(':') {
+            Some(colon) => (colon, inner.start + colon + 1),
+            None => (text.len(), inner.end),
+        };
+        let argument_range = inner.start..inner.start + argument_len;
+        let spec_range = spec_start..inner.end;
+
+        let argument = match parse_argument(&text[..argument_len]) {
+            Some(argument) => argument,
+            None => {
+                self.error(argument_range, FormatError::InvalidArgument);
+                return None;
+            }
+        };
+        let spec = match parse_spec(&self.value[spec_range.clone()]) {
+            Ok(spec) => spec,
+            Err(error_start) => {
+                self.error(
+                    spec_range.start + error_start..spec_range.end,
+                    FormatError::InvalidSpec,
+                );
+                return None;
+            }
+        };
+        Some(Placeholder {
+            argument,
+            argument_range: self.map.to_source_range(argument_range),
+            spec,
+            spec_range: self.map.to_source_range(spec_range),
+        })
+    }
+}
+
+fn parse_argument(text: &str) -> Option<FormatArgument> {
+    if text.is_empty() {
+        return Some(FormatArgument::Next);
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().ok().map(FormatArgument::Index);
+    }
+    if identifier_len(text) == text.len() {
+        return Some(FormatArgument::Name(text.to_string()));
+    }
+    None
+}
+
+/// Returns the position of the first invalid character on error.
+fn parse_spec(spec: &str) -> Result<FormatSpec, usize> {
+    let mut res = FormatSpec::default();
+    let mut rest = spec;
+
+    let mut chars = rest.chars();
+    let (first, second) = (chars.next(), chars.next());
+    if let Some(align) = second.and_then(align_from_char) {
+        res.fill = first;
+        res.align = Some(align);
+        rest = chars.as_str();
+    } else if let Some(align) = first.and_then(align_from_char) {
+        res.align = Some(align);
+        rest = &rest[1..];
+    }
+
+    if let Some(r) = rest.strip_prefix('+') {
+        res.sign = Some(FormatSign::Plus);
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix('-') {
+        res.sign = Some(FormatSign::Minus);
+        rest = r;
+    }
+    if let Some(r) = rest.strip_prefix('#') {
+        res.alternate = true;
+        rest = r;
+    }
+    // `0$` is a width taken from the first argument, not zero padding.
+    if rest.starts_with('0') && !rest[1..].starts_with('$') {
+        res.zero_pad = true;
+        rest = &rest[1..];
+    }
+    if let Some((count, r)) = parse_count(rest) {
+        res.width = Some(count);
+        rest = r;
+    }
+    if let Some(r) = rest.strip_prefix('.') {
+        let (count, r) = match r.strip_prefix('*') {
+            Some(r) => (FormatCount::Star, r),
+            None => parse_count(r).ok_or(spec.len() - rest.len())?,
+        };
+        res.precision = Some(count);
+        rest = r;
+    }
+
+    let ty = rest.strip_suffix('?').unwrap_or(rest);
+    if !ty.is_empty() && identifier_len(ty) != ty.len() {
+        return Err(spec.len() - rest.len());
+    }
+    res.ty = rest.to_string();
+    Ok(res)
+}
+
+fn parse_count(text: &str) -> Option<(FormatCount, &str)> {
+    let n_digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    if n_digits > 0 {
+        let value = text[..n_digits].parse().ok()?;
+        let rest = &text[n_digits..];
+        return Some(match rest.strip_prefix('$') {
+            Some(rest) => (FormatCount::Argument(FormatArgument::Index(value)), rest),
+            None => (FormatCount::Is(value), rest),
+        });
+    }
+    let n_ident = identifier_len(text);
+    let rest = text[n_ident..].strip_prefix('$');
+    match rest {
+        Some(rest) if n_ident > 0 => {
+            let name = text[..n_ident].to_string();
+            Some((FormatCount::Argument(FormatArgument::Name(name)), rest))
+        }
+        _ => None,
+    }
+}
+
+fn align_from_char(c: char) -> Option<FormatAlign> {
+    match c {
+        '<' => Some(FormatAlign::Left),
+        '^' => Some(FormatAlign::Center),
+        '>' => Some(FormatAlign::Right),
+        _ => None,
+    }
+}
+
+/// Returns the length of the identifier at the start of `text`, or zero.
+fn identifier_len(text: &str) -> usize {
+    let mut chars = text.char_indices();
+    match chars.next() {
+        Some((_, c)) if c.is_alphabetic() || c == '_' => (),
+        _ => return 0,
+    }
+    let len =
+        chars.find(|&(_, c)| !(c.is_alphanumeric() || c == '_')).map_or(text.len(), |(i, _)| i);
+    if &text[..len] == "_" {
+        return 0;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(range: Range<usize>, text: &str) -> FormatPiece {
+        FormatPiece { range, kind: FormatPieceKind::Text(text.to_string()) }
+    }
+
+    fn placeholder(
+        range: Range<usize>,
+        argument: FormatArgument,
+        argument_range: Range<usize>,
+        spec: FormatSpec,
+        spec_range: Range<usize>,
+    ) -> FormatPiece {
+        let placeholder = Placeholder { argument, argument_range, spec, spec_range };
+        FormatPiece { range, kind: FormatPieceKind::Placeholder(placeholder) }
+    }
+
+    #[test]
+    fn test_parse_format_str() {
+        fn check(literal_text: &str, expected: &[FormatPiece]) {
+            assert_eq!(parse_format_str(literal_text), Ok(expected.to_vec()));
+        }
+
+        check("", &[]);
+        check("hello {{world}}", &[text(0..15, "hello {world}")]);
+        check(
+            "a {} b {0} c {name}",
+            &[
+                text(0..2, "a "),
+                placeholder(2..4, FormatArgument::Next, 3..3, FormatSpec::default(), 3..3),
+                text(4..7, " b "),
+                placeholder(7..10, FormatArgument::Index(0), 8..9, FormatSpec::default(), 9..9),
+                text(10..13, " c "),
+                placeholder(
+                    13..19,
+                    FormatArgument::Name("name".to_string()),
+                    14..18,
+                    FormatSpec::default(),
+                    18..18,
+                ),
+            ],
+        );
+        check(
+            r"\n{x:>8.2}\t",
+            &[
+                text(0..2, "\n"),
+                placeholder(
+                    2..10,
+                    FormatArgument::Name("x".to_string()),
+                    3..4,
+                    FormatSpec {
+                        align: Some(FormatAlign::Right),
+                        width: Some(FormatCount::Is(8)),
+                        precision: Some(FormatCount::Is(2)),
+                        ..FormatSpec::default()
+                    },
+                    5..9,
+                ),
+                text(10..12, "\t"),
+            ],
+        );
+        // Braces written as escapes are braces too.
+        check(
+            r"\u{7b}:?\u{7d}",
+            &[placeholder(
+                0..14,
+                FormatArgument::Next,
+                6..6,
+                FormatSpec { ty: "?".to_string(), ..FormatSpec::default() },
+                7..8,
+            )],
+        );
+    }
+
+    #[test]
+    fn test_parse_format_spec() {
+        fn check(spec: &str, expected: FormatSpec) {
+            assert_eq!(parse_spec(spec), Ok(expected));
+        }
+
+        let default = FormatSpec::default();
+        check("", default.clone());
+        check("x?", FormatSpec { ty: "x?".to_string(), ..default.clone() });
+        check(
+            "*^+#010.3e",
+            FormatSpec {
+                fill: Some('*'),
+                align: Some(FormatAlign::Center),
+                sign: Some(FormatSign::Plus),
+                alternate: true,
+                zero_pad: true,
+                width: Some(FormatCount::Is(10)),
+                precision: Some(FormatCount::Is(3)),
+                ty: "e".to_string(),
+            },
+        );
+        check(
+            "ы<-0$.*",
+            FormatSpec {
+                fill: Some('ы'),
+                align: Some(FormatAlign::Left),
+                sign: Some(FormatSign::Minus),
+                width: Some(FormatCount::Argument(FormatArgument::Index(0))),
+                precision: Some(FormatCount::Star),
+                ..default.clone()
+            },
+        );
+        check(
+            "width$.prec$x",
+            FormatSpec {
+                width: Some(FormatCount::Argument(FormatArgument::Name("width".to_string()))),
+                precision: Some(FormatCount::Argument(FormatArgument::Name("prec".to_string()))),
+                ty: "x".to_string(),
+                ..default
+            },
+        );
+        assert_eq!(parse_spec("8.x"), Err(1));
+        assert_eq!(parse_spec("8 x"), Err(1));
+    }
+
+    #[test]
+    fn test_parse_format_str_errors() {
+        fn check(literal_text: &str, expected: &[(Range<usize>, FormatError)]) {
+            let actual = parse_format_str(literal_text);
+            assert_eq!(actual.as_ref().map_err(|it| it.as_slice()), Err(expected));
+        }
+
+        check("a } b", &[(2..3, FormatError::UnmatchedCloseBrace)]);
+        check("a {} {", &[(5..6, FormatError::UnmatchedOpenBrace)]);
+        check(
+            "} {0",
+            &[(0..1, FormatError::UnmatchedCloseBrace), (2..3, FormatError::UnmatchedOpenBrace)],
+        );
+        check(r"\x7d", &[(0..4, FormatError::UnmatchedCloseBrace)]);
+        check(
+            "{a b} {_}",
+            &[(1..4, FormatError::InvalidArgument), (7..8, FormatError::InvalidArgument)],
+        );
+        check(
+            "{:8.} {:x!}",
+            &[(3..4, FormatError::InvalidSpec), (8..10, FormatError::InvalidSpec)],
+        );
+        check(r"{}\q", &[(2..4, FormatError::Escape(EscapeError::InvalidEscape))]);
+        assert_eq!(
+            FormatError::UnmatchedCloseBrace.to_string(),
+            "invalid format string: unmatched `}` found"
+        );
+    }
+}
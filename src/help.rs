@@ -0,0 +1,245 @@
+//! Help notes and machine-applicable fixes for errors in literals.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::{EscapeError, Mode, escape_char, scan_escape, unescape_char};
+
+/// A help note for an error, with a suggested replacement of a part of the
+/// literal, if there's an obvious one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Help {
+    pub message: String,
+    pub suggestion: Option<(Range<usize>, String)>,
+}
+
+impl Help {
+    fn note(message: &str) -> Help {
+        Help { message: message.to_string(), suggestion: None }
+    }
+
+    fn fix(message: String, range: Range<usize>, replacement: String) -> Help {
+        Help { message, suggestion: Some((range, replacement)) }
+    }
+}
+
+impl EscapeError {
+    /// Returns a help for this error, found at `range` of `literal_text`, a
+    /// contents of a literal (without quotes) in `mode`. Ranges of
+    /// suggestions are relative to `literal_text` as well.
+    pub fn help(&self, literal_text: &str, range: Range<usize>, mode: Mode) -> Option<Help> {
+        let escape = &literal_text[range.clone()];
+        let help = match self {
+            EscapeError::ZeroChars
+            | EscapeError::LoneSlash
+            | EscapeError::LoneSurrogateUnicodeEscape
+            | EscapeError::OutOfRangeUnicodeEscape
+            | EscapeError::NulInCStr => return None,
+
+            EscapeError::MoreThanOneChar => {
+                Help::note("if you meant to write a string literal, use double quotes")
+            }
+            EscapeError::InvalidEscape => Help::fix(
+                "if you meant to write a literal backslash, escape it".to_string(),
+                range.start..range.start + 1,
+                r"\\".to_string(),
+            ),
+            EscapeError::EscapeOnlyChar => {
+                let c = escape.chars().next()?;
+                Help::fix(
+                    "escape the character".to_string(),
+                    range,
+                    escape_char(c, if mode.in_single_quotes() { Mode::Char } else { Mode::Str }),
+                )
+            }
+            EscapeError::TooShortHexEscape | EscapeError::InvalidCharInHexEscape => {
+                Help::note(r"format of hex escapes is `\xHH`")
+            }
+            EscapeError::OutOfRangeHexEscape => {
+                let value = u8::from_str_radix(escape.get(2..4)?, 16).ok()?;
+                Help::fix(
+                    "if you want to write a Unicode character, use a unicode escape".to_string(),
+                    range,
+                    format!("\\u{{{:x}}}", value),
+                )
+            }
+            EscapeError::NoBraceInUnicodeEscape => {
+                let digits = &literal_text[range.start + 2..];
+                let n_digits = digits.bytes().take_while(u8::is_ascii_hexdigit).count();
+                if n_digits == 0 {
+                    return Some(Help::note(r"format of unicode escapes is `\u{...}`"));
+                }
+                let replacement = format!("\\u{{{}}}", &digits[..n_digits]);
+                if !is_valid_escape(&replacement, mode) {
+                    return Some(Help::note(r"format of unicode escapes is `\u{...}`"));
+                }
+                Help::fix(
+                    r"format of unicode escapes is `\u{...}`".to_string(),
+                    range.start..range.start + 2 + n_digits,
+                    replacement,
+                )
+            }
+            EscapeError::InvalidCharInUnicodeEscape => {
+                Help::note("unicode escapes may only contain hex digits and underscores")
+            }
+            EscapeError::EmptyUnicodeEscape => {
+                Help::note("this escape must have at least 1 hex digit")
+            }
+            EscapeError::UnclosedUnicodeEscape => {
+                let digits = escape.get(3..)?;
+                let n_digits =
+                    digits.bytes().take_while(|&b| b.is_ascii_hexdigit() || b == b'_').count();
+                if n_digits == 0 {
+                    return None;
+                }
+                if !is_valid_escape(&format!("\\u{{{}}}", &digits[..n_digits]), mode) {
+                    return Some(Help::note(r"format of unicode escapes is `\u{...}`"));
+                }
+                let end = range.start + 3 + n_digits;
+                Help::fix("terminate the unicode escape".to_string(), end..end, "}".to_string())
+            }
+            EscapeError::LeadingUnderscoreUnicodeEscape => {
+                // The error ends at the first underscore, so count the rest of
+                // them in the literal.
+                let start = range.start + 3;
+                let rest = &literal_text[start..];
+                let n_underscores = rest.bytes().take_while(|&b| b == b'_').count();
+                if !is_valid_escape(&format!("\\u{{{}", &rest[n_underscores..]), mode) {
+                    return Some(Help::note("unicode escapes may not start with underscores"));
+                }
+                Help::fix(
+                    "remove the leading underscores".to_string(),
+                    start..start + n_underscores,
+                    String::new(),
+                )
+            }
+            EscapeError::OverlongUnicodeEscape => {
+                let (open, close) = match (escape.find('{'), escape.rfind('}')) {
+                    (Some(open), Some(close)) if open < close => (open, close),
+                    _ => return Some(Help::note(r"format of unicode escapes is `\u{...}`")),
+                };
+                let digits: String = escape[open + 1..close].replace('_', "");
+                let significant = digits.trim_start_matches('0');
+                let significant = if significant.is_empty() { "0" } else { significant };
+                let replacement = format!("\\u{{{}}}", significant);
+                if !is_valid_escape(&replacement, mode) {
+                    return Some(Help::note("unicode escapes may have at most 6 hex digits"));
+                }
+                Help::fix("remove the leading zeros".to_string(), range, replacement)
+            }
+            EscapeError::UnicodeEscapeInByte => {
+                let c = unescape_char(escape).ok()?;
+                utf8_bytes_help(c, range)
+            }
+            EscapeError::NonAsciiCharInByte => {
+                let c = escape.chars().next()?;
+                utf8_bytes_help(c, range)
+            }
+            EscapeError::NonAsciiCharInByteString => {
+                Help::note("raw byte strings may only contain ASCII characters")
+            }
+            EscapeError::BareCarriageReturn => {
+                Help::fix("escape the carriage return".to_string(), range, r"\r".to_string())
+            }
+            EscapeError::BareCarriageReturnInRawString => {
+                Help::note("use a non-raw string and escape the carriage return as `\\r`")
+            }
+        };
+        Some(help)
+    }
+}
+
+/// Whether `text` starts with an escape which is valid in `mode`, so that a
+/// suggestion doesn't trade one error for another.
+fn is_valid_escape(text: &str, mode: Mode) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|first_char| scan_escape(first_char, &mut chars, mode).is_ok())
+}
+
+fn utf8_bytes_help(c: char, range: Range<usize>) -> Help {
+    let mut replacement = String::new();
+    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+        write!(replacement, "\\x{:02X}", b).unwrap();
+    }
+    Help::fix(
+        format!("if you meant to use the UTF-8 encoding of {:?}, use \\xHH escapes", c),
+        range,
+        replacement,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unescape_str;
+
+    #[test]
+    fn test_escape_error_help() {
+        fn check(literal_text: &str, mode: Mode, expected_fix: Option<&str>) {
+            let mut errors = Vec::new();
+            unescape_str(literal_text, &mut |range, res| {
+                if let Err(err) = res {
+                    errors.push((range, err))
+                }
+            });
+            let (range, err) = errors.pop().unwrap();
+            let help = err.help(literal_text, range, mode).unwrap();
+            let fixed = help.suggestion.map(|(range, replacement)| {
+                let mut fixed = literal_text.to_string();
+                fixed.replace_range(range, &replacement);
+                fixed
+            });
+            assert_eq!(fixed.as_deref(), expected_fix);
+        }
+
+        check(r"a\qb", Mode::Str, Some(r"a\\qb"));
+        check("a\"b", Mode::Str, Some(r#"a\"b"#));
+        check(r"\u1234x", Mode::Str, Some(r"\u{1234}x"));
+        check(r"\u(1234)", Mode::Str, None);
+        check(r"\x80", Mode::Str, Some(r"\u{80}"));
+        check(r"\u{_1_2}", Mode::Str, Some(r"\u{1_2}"));
+        check(r"\u{__1}", Mode::Str, Some(r"\u{1}"));
+        check(r"\u{_}", Mode::Str, None);
+        check(r"\u12345678", Mode::Str, None);
+        check(r"\uD800", Mode::Str, None);
+        check(r"\u{0000FFFFFF}", Mode::Str, None);
+        check(r"\u{D800", Mode::Str, None);
+        check(r"\u{1234567", Mode::Str, None);
+        check(r"\u{41", Mode::Str, Some(r"\u{41}"));
+        check(r"\u{00000041}", Mode::Str, Some(r"\u{41}"));
+        check(r"\u{0_0000_0000}", Mode::Str, Some(r"\u{0}"));
+        check(r"\u{1000000}", Mode::Str, None);
+        check("a\rb", Mode::Str, Some(r"a\rb"));
+    }
+
+    #[test]
+    fn test_byte_error_help() {
+        let help = EscapeError::NonAsciiCharInByte.help("aыb", 1..3, Mode::ByteStr).unwrap();
+        assert_eq!(
+            help,
+            Help {
+                message: "if you meant to use the UTF-8 encoding of 'ы', use \\xHH escapes"
+                    .to_string(),
+                suggestion: Some((1..3, r"\xD1\x8B".to_string())),
+            }
+        );
+        let help = EscapeError::UnicodeEscapeInByte.help(r"\u{1F63b}", 0..9, Mode::Byte).unwrap();
+        assert_eq!(help.suggestion, Some((0..9, r"\xF0\x9F\x98\xBB".to_string())));
+        let help = EscapeError::EscapeOnlyChar.help("'", 0..1, Mode::Byte).unwrap();
+        assert_eq!(help.suggestion, Some((0..1, r"\'".to_string())));
+        let help = EscapeError::MoreThanOneChar.help("ab", 0..2, Mode::Char).unwrap();
+        assert_eq!(help.suggestion, None);
+    }
+
+    #[test]
+    fn test_overlong_help_without_braces() {
+        for &literal_text in [r"\u{0000000ы", r"\u0000041"].iter() {
+            let recovered = crate::unescape_char_recovering(literal_text);
+            let err =
+                recovered.errors.iter().find(|it| it.error == EscapeError::OverlongUnicodeEscape);
+            let err = err.unwrap();
+            let help = err.error.help(literal_text, err.range.clone(), Mode::Char).unwrap();
+            assert_eq!(help.suggestion, None);
+        }
+    }
+}
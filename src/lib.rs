@@ -1,10 +1,59 @@
 //! Utilities for validating string and char literals and turning them into
 //! values they represent.
 
+mod diagnostic;
+mod escape;
+mod format;
+mod help;
+mod literal;
+mod lossy;
+mod number;
+mod raw;
+mod recovery;
+mod render;
+mod segments;
+mod source_map;
+mod suffix;
+mod validate;
+mod warning;
+
+use std::borrow::Cow;
+use std::fmt;
 use std::str::Chars;
 use std::ops::Range;
 
-#[derive(Debug, PartialEq, Eq)]
+pub use crate::diagnostic::{Diagnostic, DiagnosticKind, diagnostics, raw_diagnostics};
+pub use crate::escape::{
+    EscapeOptions, escape_char, escape_char_with, escape_str, escape_str_with, escape_byte,
+    escape_byte_with, escape_byte_str, escape_byte_str_with,
+};
+pub use crate::format::{
+    FormatAlign, FormatArgument, FormatCount, FormatError, FormatPiece, FormatPieceKind,
+    FormatSign, FormatSpec, Placeholder, parse_format_str,
+};
+pub use crate::help::Help;
+pub use crate::literal::{Literal, LiteralError, LiteralKind, LiteralValue, unescape_literal};
+pub use crate::lossy::{
+    unescape_byte_lossy, unescape_byte_str_lossy, unescape_c_str_lossy, unescape_char_lossy,
+    unescape_raw_byte_str_lossy, unescape_raw_c_str_lossy, unescape_raw_str_lossy,
+    unescape_str_lossy,
+};
+pub use crate::number::{
+    Base, FloatLiteral, FloatValue, IntLiteral, NumberError, PointerWidth, parse_float_literal,
+    parse_int_literal,
+};
+pub use crate::raw::{
+    MAX_RAW_STR_HASHES, RawFormError, RawStrError, lex_raw_str, raw_byte_str_hashes, raw_str_hashes,
+};
+pub use crate::recovery::{Recovered, unescape_byte_recovering, unescape_char_recovering};
+pub use crate::render::{RenderOptions, render_literal};
+pub use crate::segments::{Segment, SegmentKind, Segments, segments};
+pub use crate::source_map::{SourceMap, unescape_byte_str_mapped, unescape_str_mapped};
+pub use crate::suffix::{SuffixError, SuffixKind, SuffixTarget, check_suffix};
+pub use crate::validate::{validate, validate_all, validate_raw, validate_raw_all};
+pub use crate::warning::{EscapeWarning, Severity, line_continuation_warnings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EscapeError {
     ZeroChars,
     MoreThanOneChar,
@@ -29,14 +78,53 @@ pub enum EscapeError {
     UnicodeEscapeInByte,
     NonAsciiCharInByte,
     NonAsciiCharInByteString,
+
+    NulInCStr,
+
+    BareCarriageReturn,
+    BareCarriageReturnInRawString,
 }
 
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            EscapeError::ZeroChars => "empty character literal",
+            EscapeError::MoreThanOneChar => "character literal may only contain one codepoint",
+            EscapeError::LoneSlash => "invalid trailing slash in literal",
+            EscapeError::InvalidEscape => "unknown character escape",
+            EscapeError::EscapeOnlyChar => "character constant must be escaped",
+            EscapeError::TooShortHexEscape => "numeric character escape is too short",
+            EscapeError::InvalidCharInHexEscape => "invalid character in numeric character escape",
+            EscapeError::OutOfRangeHexEscape => {
+                "out of range hex escape, must be a character in the range [\\x00-\\x7f]"
+            }
+            EscapeError::NoBraceInUnicodeEscape => "incorrect unicode escape sequence",
+            EscapeError::InvalidCharInUnicodeEscape => "invalid character in unicode escape",
+            EscapeError::EmptyUnicodeEscape => "empty unicode escape",
+            EscapeError::UnclosedUnicodeEscape => "unterminated unicode escape",
+            EscapeError::LeadingUnderscoreUnicodeEscape => "invalid start of unicode escape: `_`",
+            EscapeError::OverlongUnicodeEscape => "unicode escape must have at most 6 hex digits",
+            EscapeError::LoneSurrogateUnicodeEscape => "unicode escape must not be a surrogate",
+            EscapeError::OutOfRangeUnicodeEscape => "unicode escape must be at most 10FFFF",
+            EscapeError::UnicodeEscapeInByte => "unicode escape in byte string",
+            EscapeError::NonAsciiCharInByte => "non-ASCII character in byte literal",
+            EscapeError::NonAsciiCharInByteString => {
+                "non-ASCII character in raw byte string literal"
+            }
+            EscapeError::NulInCStr => "null characters in C string literals are not supported",
+            EscapeError::BareCarriageReturn => "bare CR not allowed in string, use `\\r` instead",
+            EscapeError::BareCarriageReturnInRawString => "bare CR not allowed in raw string",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for EscapeError {}
+
 /// Takes a contents of a char literal (without quotes), and returns an
 /// unescaped char or an error
-pub fn unescape_char(literal_text: &str) -> Result<char, (usize, EscapeError)> {
-    let mut chars = literal_text.chars();
-    unescape_char_or_byte(&mut chars, Mode::Char)
-        .map_err(|err| (literal_text.len() - chars.as_str().len(), err))
+pub fn unescape_char(literal_text: &str) -> Result<char, SpannedError> {
+    unescape_char_or_byte(literal_text, Mode::Char)
 }
 
 /// Takes a contents of a string literal (without quotes) and produces a
@@ -45,14 +133,89 @@ pub fn unescape_str<F>(literal_text: &str, callback: &mut F)
 where
     F: FnMut(Range<usize>, Result<char, EscapeError>),
 {
-    unescape_str_or_byte_str(literal_text, Mode::Str, callback)
+    UnescapeStr::new(literal_text).for_each(|(range, res)| callback(range, res))
 }
 
-pub fn unescape_byte(literal_text: &str) -> Result<u8, (usize, EscapeError)> {
-    let mut chars = literal_text.chars();
-    unescape_char_or_byte(&mut chars, Mode::Byte)
-        .map(byte_from_char)
-        .map_err(|err| (literal_text.len() - chars.as_str().len(), err))
+pub fn unescape_byte(literal_text: &str) -> Result<u8, SpannedError> {
+    unescape_char_or_byte(literal_text, Mode::Byte).map(byte_from_char)
+}
+
+/// An error in a char or byte literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedError {
+    /// The whole offending escape sequence.
+    pub range: Range<usize>,
+    /// The part of `range` to point at, if it is narrower than the escape.
+    pub primary_range: Option<Range<usize>>,
+    pub error: EscapeError,
+}
+
+impl EscapeError {
+    /// Given the `range` of the escape sequence with this error in
+    /// `literal_text`, returns the narrower range of its offending part, if
+    /// there is one. Works for ranges reported by all `unescape_*`
+    /// functions in `mode`.
+    pub fn primary_range(
+        &self,
+        literal_text: &str,
+        range: Range<usize>,
+        mode: Mode,
+    ) -> Option<Range<usize>> {
+        let escape = &literal_text[range.clone()];
+        let last_char = || {
+            let c = escape.chars().next_back()?;
+            Some(range.end - c.len_utf8()..range.end)
+        };
+        let digits = || {
+            let open = escape.find('{')?;
+            let close = escape.rfind('}')?;
+            Some(range.start + open + 1..range.start + close)
+        };
+        match self {
+            EscapeError::MoreThanOneChar => {
+                let mut chars = escape.chars();
+                let first_char = chars.next()?;
+                let _ = scan_escape(first_char, &mut chars, mode);
+                Some(range.end - chars.as_str().len()..range.end)
+            }
+            EscapeError::InvalidEscape => Some(range.start + 1..range.end),
+            EscapeError::NoBraceInUnicodeEscape if escape.len() > 2 => {
+                Some(range.start + 2..range.end)
+            }
+            EscapeError::InvalidCharInHexEscape | EscapeError::InvalidCharInUnicodeEscape => {
+                last_char()
+            }
+            EscapeError::OutOfRangeHexEscape => Some(range.start + 2..range.end),
+            EscapeError::LeadingUnderscoreUnicodeEscape => {
+                let start = range.start + escape.find('_')?;
+                Some(start..start + 1)
+            }
+            EscapeError::OverlongUnicodeEscape => {
+                let digits = digits()?;
+                let (seventh, _) = literal_text[digits.clone()]
+                    .char_indices()
+                    .filter(|&(_, c)| c != '_')
+                    .nth(6)?;
+                Some(digits.start + seventh..digits.end)
+            }
+            EscapeError::LoneSurrogateUnicodeEscape | EscapeError::OutOfRangeUnicodeEscape => {
+                digits()
+            }
+            EscapeError::ZeroChars
+            | EscapeError::LoneSlash
+            | EscapeError::EscapeOnlyChar
+            | EscapeError::NoBraceInUnicodeEscape
+            | EscapeError::TooShortHexEscape
+            | EscapeError::EmptyUnicodeEscape
+            | EscapeError::UnclosedUnicodeEscape
+            | EscapeError::UnicodeEscapeInByte
+            | EscapeError::NonAsciiCharInByte
+            | EscapeError::NonAsciiCharInByteString
+            | EscapeError::NulInCStr
+            | EscapeError::BareCarriageReturn
+            | EscapeError::BareCarriageReturnInRawString => None,
+        }
+    }
 }
 
 /// Takes a contents of a string literal (without quotes) and produces a
@@ -61,9 +224,7 @@ pub fn unescape_byte_str<F>(literal_text: &str, callback: &mut F)
 where
     F: FnMut(Range<usize>, Result<u8, EscapeError>),
 {
-    unescape_str_or_byte_str(literal_text, Mode::ByteStr, &mut |range, char| {
-        callback(range, char.map(byte_from_char))
-    })
+    UnescapeByteStr::new(literal_text).for_each(|(range, res)| callback(range, res))
 }
 
 /// Takes a contents of a string literal (without quotes) and produces a
@@ -74,7 +235,7 @@ pub fn unescape_raw_str<F>(literal_text: &str, callback: &mut F)
 where
     F: FnMut(Range<usize>, Result<char, EscapeError>),
 {
-    unescape_raw_str_or_byte_str(literal_text, Mode::Str, callback)
+    UnescapeRawStr::new(literal_text).for_each(|(range, res)| callback(range, res))
 }
 
 /// Takes a contents of a string literal (without quotes) and produces a
@@ -85,24 +246,360 @@ pub fn unescape_raw_byte_str<F>(literal_text: &str, callback: &mut F)
 where
     F: FnMut(Range<usize>, Result<u8, EscapeError>),
 {
-    unescape_raw_str_or_byte_str(literal_text, Mode::ByteStr, &mut |range, char| {
-        callback(range, char.map(byte_from_char))
+    UnescapeRawByteStr::new(literal_text).for_each(|(range, res)| callback(range, res))
+}
+
+/// Takes a contents of a C string literal (without quotes) and produces a
+/// sequence of escaped characters and bytes or errors.
+pub fn unescape_c_str<F>(literal_text: &str, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<MixedUnit, EscapeError>),
+{
+    UnescapeCStr::new(literal_text).for_each(|(range, res)| callback(range, res))
+}
+
+/// Takes a contents of a C string literal (without quotes) and produces a
+/// sequence of characters or errors.
+/// NOTE: Raw strings do not perform any explicit character escaping, here we
+/// only translate CRLF to LF and produce errors on bare CR.
+pub fn unescape_raw_c_str<F>(literal_text: &str, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<MixedUnit, EscapeError>),
+{
+    UnescapeRawCStr::new(literal_text).for_each(|(range, res)| callback(range, res))
+}
+
+/// Like `unescape_str`, but produces runs of characters which stand for
+/// themselves as a single `Chunk::Run`, instead of char by char.
+pub fn unescape_str_chunks<'a, F>(literal_text: &'a str, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<Chunk<'a, char>, EscapeError>),
+{
+    UnescapeStrChunks::new(literal_text).for_each(|(range, res)| callback(range, res))
+}
+
+/// Like `unescape_byte_str`, but produces runs of bytes which stand for
+/// themselves as a single `Chunk::Run`, instead of byte by byte.
+pub fn unescape_byte_str_chunks<'a, F>(literal_text: &'a str, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<Chunk<'a, u8>, EscapeError>),
+{
+    UnescapeByteStrChunks::new(literal_text).for_each(|(range, res)| callback(range, res))
+}
+
+/// A piece of an unescaped string literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunk<'a, T> {
+    /// A run of characters of the literal which stand for themselves. In byte
+    /// strings, the run is always ASCII.
+    Run(&'a str),
+    /// A single unit produced by an escape or some other special character.
+    Unit(T),
+}
+
+/// All errors found in a literal, together with their ranges.
+pub type EscapeErrors = Vec<(Range<usize>, EscapeError)>;
+
+/// Unescapes a contents of a string literal (without quotes) into a string,
+/// borrowing `literal_text` if it has no escapes or line continuations.
+/// Returns all errors if the literal is invalid.
+pub fn unescape_str_cow(literal_text: &str) -> Result<Cow<'_, str>, EscapeErrors> {
+    let needs_owned = literal_text.contains(&['\\', '\r'][..]);
+    let chunks = UnescapeStrChunks::new(literal_text);
+    collect_cow(literal_text, needs_owned, chunks, |buf: &mut String, chunk| match chunk {
+        Chunk::Run(run) => buf.push_str(run),
+        Chunk::Unit(c) => buf.push(c),
     })
 }
 
-#[derive(Debug, Clone, Copy)]
+pub fn unescape_byte_str_cow(literal_text: &str) -> Result<Cow<'_, [u8]>, EscapeErrors> {
+    let needs_owned = literal_text.contains(&['\\', '\r'][..]);
+    let chunks = UnescapeByteStrChunks::new(literal_text);
+    collect_cow(
+        literal_text.as_bytes(),
+        needs_owned,
+        chunks,
+        |buf: &mut Vec<u8>, chunk| match chunk {
+            Chunk::Run(run) => buf.extend_from_slice(run.as_bytes()),
+            Chunk::Unit(b) => buf.push(b),
+        },
+    )
+}
+
+pub fn unescape_raw_str_cow(literal_text: &str) -> Result<Cow<'_, str>, EscapeErrors> {
+    let needs_owned = literal_text.contains('\r');
+    collect_cow(
+        literal_text,
+        needs_owned,
+        UnescapeRawStr::new(literal_text),
+        |buf: &mut String, c| buf.push(c),
+    )
+}
+
+pub fn unescape_raw_byte_str_cow(literal_text: &str) -> Result<Cow<'_, [u8]>, EscapeErrors> {
+    let needs_owned = literal_text.contains('\r');
+    collect_cow(
+        literal_text.as_bytes(),
+        needs_owned,
+        UnescapeRawByteStr::new(literal_text),
+        Vec::push,
+    )
+}
+
+/// Unescapes a contents of a C string literal (without quotes) into bytes,
+/// without the terminating NUL.
+pub fn unescape_c_str_cow(literal_text: &str) -> Result<Cow<'_, [u8]>, EscapeErrors> {
+    let needs_owned = literal_text.contains(&['\\', '\r'][..]);
+    collect_cow(
+        literal_text.as_bytes(),
+        needs_owned,
+        UnescapeCStr::new(literal_text),
+        |buf, unit| unit.push_to(buf),
+    )
+}
+
+pub fn unescape_raw_c_str_cow(literal_text: &str) -> Result<Cow<'_, [u8]>, EscapeErrors> {
+    let needs_owned = literal_text.contains('\r');
+    collect_cow(
+        literal_text.as_bytes(),
+        needs_owned,
+        UnescapeRawCStr::new(literal_text),
+        |buf, unit| unit.push_to(buf),
+    )
+}
+
+/// If `needs_owned` is false, the value is known to be equal to `borrowed`,
+/// and `units` are only checked for errors.
+fn collect_cow<'a, B, T>(
+    borrowed: &'a B,
+    needs_owned: bool,
+    units: impl Iterator<Item = (Range<usize>, Result<T, EscapeError>)>,
+    push: impl Fn(&mut B::Owned, T),
+) -> Result<Cow<'a, B>, EscapeErrors>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: Default,
+{
+    let mut buf = B::Owned::default();
+    let mut errors = Vec::new();
+    for (range, unit) in units {
+        match unit {
+            Ok(unit) if needs_owned => push(&mut buf, unit),
+            Ok(_) => (),
+            Err(err) => errors.push((range, err)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(if needs_owned { Cow::Owned(buf) } else { Cow::Borrowed(borrowed) })
+}
+
+/// An iterator version of `unescape_str_chunks`.
+#[derive(Debug, Clone)]
+pub struct UnescapeStrChunks<'a> {
+    inner: EscapedChars<'a>,
+}
+
+impl<'a> UnescapeStrChunks<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeStrChunks<'a> {
+        UnescapeStrChunks { inner: EscapedChars::new(literal_text, Mode::Str) }
+    }
+}
+
+impl<'a> Iterator for UnescapeStrChunks<'a> {
+    type Item = (Range<usize>, Result<Chunk<'a, char>, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((range, run)) = self.inner.next_run() {
+            return Some((range, Ok(Chunk::Run(run))));
+        }
+        let (range, res) = self.inner.next()?;
+        Some((range, res.map(Chunk::Unit)))
+    }
+}
+
+/// An iterator version of `unescape_byte_str_chunks`.
+#[derive(Debug, Clone)]
+pub struct UnescapeByteStrChunks<'a> {
+    inner: EscapedChars<'a>,
+}
+
+impl<'a> UnescapeByteStrChunks<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeByteStrChunks<'a> {
+        UnescapeByteStrChunks { inner: EscapedChars::new(literal_text, Mode::ByteStr) }
+    }
+}
+
+impl<'a> Iterator for UnescapeByteStrChunks<'a> {
+    type Item = (Range<usize>, Result<Chunk<'a, u8>, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((range, run)) = self.inner.next_run() {
+            return Some((range, Ok(Chunk::Run(run))));
+        }
+        let (range, res) = self.inner.next()?;
+        Some((range, res.map(|c| Chunk::Unit(byte_from_char(c)))))
+    }
+}
+
+/// An iterator version of `unescape_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeStr<'a> {
+    inner: EscapedChars<'a>,
+}
+
+impl<'a> UnescapeStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeStr<'a> {
+        UnescapeStr { inner: EscapedChars::new(literal_text, Mode::Str) }
+    }
+}
+
+impl Iterator for UnescapeStr<'_> {
+    type Item = (Range<usize>, Result<char, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// An iterator version of `unescape_byte_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeByteStr<'a> {
+    inner: EscapedChars<'a>,
+}
+
+impl<'a> UnescapeByteStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeByteStr<'a> {
+        UnescapeByteStr { inner: EscapedChars::new(literal_text, Mode::ByteStr) }
+    }
+}
+
+impl Iterator for UnescapeByteStr<'_> {
+    type Item = (Range<usize>, Result<u8, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, res) = self.inner.next()?;
+        Some((range, res.map(byte_from_char)))
+    }
+}
+
+/// An iterator version of `unescape_raw_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeRawStr<'a> {
+    inner: RawChars<'a>,
+}
+
+impl<'a> UnescapeRawStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeRawStr<'a> {
+        UnescapeRawStr { inner: RawChars::new(literal_text, Mode::Str) }
+    }
+}
+
+impl Iterator for UnescapeRawStr<'_> {
+    type Item = (Range<usize>, Result<char, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// An iterator version of `unescape_raw_byte_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeRawByteStr<'a> {
+    inner: RawChars<'a>,
+}
+
+impl<'a> UnescapeRawByteStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeRawByteStr<'a> {
+        UnescapeRawByteStr { inner: RawChars::new(literal_text, Mode::ByteStr) }
+    }
+}
+
+impl Iterator for UnescapeRawByteStr<'_> {
+    type Item = (Range<usize>, Result<u8, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, res) = self.inner.next()?;
+        Some((range, res.map(byte_from_char)))
+    }
+}
+
+/// An iterator version of `unescape_c_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeCStr<'a> {
+    literal_text: &'a str,
+    inner: EscapedChars<'a>,
+}
+
+impl<'a> UnescapeCStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeCStr<'a> {
+        UnescapeCStr { literal_text, inner: EscapedChars::new(literal_text, Mode::CStr) }
+    }
+}
+
+impl Iterator for UnescapeCStr<'_> {
+    type Item = (Range<usize>, Result<MixedUnit, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, res) = self.inner.next()?;
+        let is_hex_escape = self.literal_text[range.clone()].starts_with("\\x");
+        Some((range, res.and_then(|c| mixed_unit_from_char(c, is_hex_escape))))
+    }
+}
+
+/// An iterator version of `unescape_raw_c_str`.
+#[derive(Debug, Clone)]
+pub struct UnescapeRawCStr<'a> {
+    inner: RawChars<'a>,
+}
+
+impl<'a> UnescapeRawCStr<'a> {
+    pub fn new(literal_text: &'a str) -> UnescapeRawCStr<'a> {
+        UnescapeRawCStr { inner: RawChars::new(literal_text, Mode::CStr) }
+    }
+}
+
+impl Iterator for UnescapeRawCStr<'_> {
+    type Item = (Range<usize>, Result<MixedUnit, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, res) = self.inner.next()?;
+        Some((range, res.and_then(|c| mixed_unit_from_char(c, false))))
+    }
+}
+
+/// A unit of a C string value: either a char, which is encoded as UTF-8, or a
+/// single non-ASCII byte produced by a `\xNN` escape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixedUnit {
+    Char(char),
+    HighByte(u8),
+}
+
+impl MixedUnit {
+    pub fn push_to(self, buf: &mut Vec<u8>) {
+        match self {
+            MixedUnit::Char(c) => buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            MixedUnit::HighByte(b) => buf.push(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Char,
     Str,
     Byte,
     ByteStr,
+    CStr,
 }
 
 impl Mode {
     pub fn in_single_quotes(self) -> bool {
         match self {
             Mode::Char | Mode::Byte => true,
-            Mode::Str | Mode::ByteStr => false,
+            Mode::Str | Mode::ByteStr | Mode::CStr => false,
         }
     }
 
@@ -113,6 +610,14 @@ impl Mode {
     pub fn is_bytes(self) -> bool {
         match self {
             Mode::Byte | Mode::ByteStr => true,
+            Mode::Char | Mode::Str | Mode::CStr => false,
+        }
+    }
+
+    /// Whether `\xNN` escapes may go above `\x7F`.
+    pub fn allows_high_bytes(self) -> bool {
+        match self {
+            Mode::Byte | Mode::ByteStr | Mode::CStr => true,
             Mode::Char | Mode::Str => false,
         }
     }
@@ -120,176 +625,386 @@ impl Mode {
 
 
 fn scan_escape(first_char: char, chars: &mut Chars<'_>, mode: Mode) -> Result<char, EscapeError> {
+    let mut error = None;
+    let res = scan_escape_with(first_char, chars, mode, &mut |err, _| {
+        error = Some(err);
+        false
+    });
+    match error {
+        Some(err) => Err(err),
+        None => Ok(res.expect("there's always a value without errors")),
+    }
+}
+
+/// The grammar of escapes behind `scan_escape`. Errors are passed to
+/// `on_error` together with the length of the text left in `chars` at the
+/// point where they are found, and `on_error` returns whether to go on. If it
+/// does, the scan recovers from the error and makes a best guess about the
+/// value, if there is one.
+fn scan_escape_with<F>(
+    first_char: char,
+    chars: &mut Chars<'_>,
+    mode: Mode,
+    on_error: &mut F,
+) -> Option<char>
+where
+    F: FnMut(EscapeError, usize) -> bool,
+{
+    let mut report =
+        |err, chars: &Chars<'_>| if on_error(err, chars.as_str().len()) { Some(()) } else { None };
+
     if first_char != '\\' {
-        return match first_char {
-            '\t' | '\n' => Err(EscapeError::EscapeOnlyChar),
-            '\'' if mode.in_single_quotes() => Err(EscapeError::EscapeOnlyChar),
-            '"' if mode.in_double_quotes() => Err(EscapeError::EscapeOnlyChar),
-            _ => {
-                if mode.is_bytes() && !first_char.is_ascii() {
-                    return Err(EscapeError::NonAsciiCharInByte);
-                }
-                Ok(first_char)
+        let err = match first_char {
+            '\t' | '\n' => EscapeError::EscapeOnlyChar,
+            '\r' => EscapeError::BareCarriageReturn,
+            '\'' if mode.in_single_quotes() => EscapeError::EscapeOnlyChar,
+            '"' if mode.in_double_quotes() => EscapeError::EscapeOnlyChar,
+            _ if mode.is_bytes() && !first_char.is_ascii() => {
+                report(EscapeError::NonAsciiCharInByte, chars)?;
+                return None;
             }
+            _ => return Some(first_char),
         };
+        report(err, chars)?;
+        return Some(first_char);
     }
 
-    let second_char = chars.next().ok_or(EscapeError::LoneSlash)?;
-
-    let res = match second_char {
-        '"' => '"',
-        'n' => '\n',
-        'r' => '\r',
-        't' => '\t',
-        '\\' => '\\',
-        '\'' => '\'',
-        '0' => '\0',
+    let second_char = match chars.next() {
+        Some(c) => c,
+        None => {
+            report(EscapeError::LoneSlash, chars)?;
+            return Some('\\');
+        }
+    };
 
+    match second_char {
         'x' => {
-            let hi = chars.next().ok_or(EscapeError::TooShortHexEscape)?;
-            let hi = hi.to_digit(16).ok_or(EscapeError::InvalidCharInHexEscape)?;
-
-            let lo = chars.next().ok_or(EscapeError::TooShortHexEscape)?;
-            let lo = lo.to_digit(16).ok_or(EscapeError::InvalidCharInHexEscape)?;
-
-            let value = hi * 16 + lo;
-
-            if !mode.is_bytes() && !is_ascii(value) {
-                return Err(EscapeError::OutOfRangeHexEscape);
+            let mut value = Some(0);
+            for _ in 0..2 {
+                let c = match chars.next() {
+                    Some(c) => c,
+                    None => {
+                        report(EscapeError::TooShortHexEscape, chars)?;
+                        return None;
+                    }
+                };
+                match c.to_digit(16) {
+                    Some(digit) => value = value.map(|value| value * 16 + digit),
+                    None => {
+                        report(EscapeError::InvalidCharInHexEscape, chars)?;
+                        value = None;
+                    }
+                }
             }
-            let value = value as u8;
-
-            value as char
+            let value = value?;
+            if !mode.allows_high_bytes() && !is_ascii(value) {
+                report(EscapeError::OutOfRangeHexEscape, chars)?;
+            }
+            Some(value as u8 as char)
         }
 
         'u' => {
-            if chars.next() != Some('{') {
-                return Err(EscapeError::NoBraceInUnicodeEscape);
+            // The char after `\u` is a part of the error, but when recovering,
+            // it is scanned as a digit.
+            let before_brace = chars.clone();
+            let has_brace = chars.next() == Some('{');
+            if !has_brace {
+                report(EscapeError::NoBraceInUnicodeEscape, chars)?;
+                *chars = before_brace;
             }
 
-            let mut n_digits = 1;
-            let mut value: u32 = match chars.next().ok_or(EscapeError::UnclosedUnicodeEscape)? {
-                '_' => return Err(EscapeError::LeadingUnderscoreUnicodeEscape),
-                '}' => return Err(EscapeError::EmptyUnicodeEscape),
-                c => c.to_digit(16).ok_or(EscapeError::InvalidCharInUnicodeEscape)?,
-            };
-
+            let mut value: u32 = 0;
+            let mut n_digits = 0;
+            let mut is_first = true;
+            let mut is_closed = false;
+            let mut has_invalid_chars = false;
             loop {
-                match chars.next() {
-                    None => return Err(EscapeError::UnclosedUnicodeEscape),
-                    Some('_') => continue,
-                    Some('}') => {
-                        if n_digits > 6 {
-                            return Err(EscapeError::OverlongUnicodeEscape);
-                        }
-                        if mode.is_bytes() {
-                            return Err(EscapeError::UnicodeEscapeInByte);
-                        }
-
-                        break std::char::from_u32(value).ok_or_else(|| {
-                            if value > 0x10FFFF {
-                                EscapeError::OutOfRangeUnicodeEscape
-                            } else {
-                                EscapeError::LoneSurrogateUnicodeEscape
-                            }
-                        })?;
+                let before = chars.clone();
+                let c = match chars.next() {
+                    Some(c) => c,
+                    None => break,
+                };
+                match c {
+                    '}' if has_brace => {
+                        is_closed = true;
+                        break;
                     }
-                    Some(c) => {
-                        let digit = c.to_digit(16).ok_or(EscapeError::InvalidCharInUnicodeEscape)?;
-                        n_digits += 1;
-                        if n_digits > 6 {
-                            continue;
+                    '_' if has_brace => {
+                        if is_first {
+                            report(EscapeError::LeadingUnderscoreUnicodeEscape, chars)?;
+                            has_invalid_chars = true;
                         }
-                        let digit = digit as u32;
-                        value = value * 16 + digit;
                     }
+                    _ => match c.to_digit(16) {
+                        Some(digit) => {
+                            n_digits += 1;
+                            if n_digits <= 6 {
+                                value = value * 16 + digit;
+                            }
+                        }
+                        None if !has_brace => {
+                            *chars = before;
+                            break;
+                        }
+                        None => {
+                            report(EscapeError::InvalidCharInUnicodeEscape, chars)?;
+                            has_invalid_chars = true;
+                        }
+                    },
+                }
+                is_first = false;
+            }
+
+            if has_brace && !is_closed {
+                report(EscapeError::UnclosedUnicodeEscape, chars)?;
+            }
+            if n_digits == 0 {
+                if is_closed && !has_invalid_chars {
+                    report(EscapeError::EmptyUnicodeEscape, chars)?;
+                }
+                return None;
+            }
+            if n_digits > 6 {
+                report(EscapeError::OverlongUnicodeEscape, chars)?;
+                return None;
+            }
+            if mode.is_bytes() {
+                report(EscapeError::UnicodeEscapeInByte, chars)?;
+            }
+            let c = std::char::from_u32(value);
+            if c.is_none() {
+                let err = if value > 0x10FFFF {
+                    EscapeError::OutOfRangeUnicodeEscape
+                } else {
+                    EscapeError::LoneSurrogateUnicodeEscape
                 };
+                report(err, chars)?;
             }
+            c
+        }
+
+        _ => match unescape_simple(second_char) {
+            Some(c) => Some(c),
+            None => {
+                report(EscapeError::InvalidEscape, chars)?;
+                Some(second_char)
+            }
+        },
+    }
+}
+
+/// Escapes which consist of a backslash and a single character, as pairs of
+/// that character and the value it stands for. Shared between `scan_escape`
+/// and the `escape_*` functions, so that both directions agree.
+const SIMPLE_ESCAPES: &[(char, char)] =
+    &[('"', '"'), ('n', '\n'), ('r', '\r'), ('t', '\t'), ('\\', '\\'), ('\'', '\''), ('0', '\0')];
+
+fn unescape_simple(escape_char: char) -> Option<char> {
+    SIMPLE_ESCAPES.iter().find(|&&(e, _)| e == escape_char).map(|&(_, c)| c)
+}
+
+fn escape_simple(c: char) -> Option<char> {
+    SIMPLE_ESCAPES.iter().find(|&&(_, v)| v == c).map(|&(e, _)| e)
+}
+
+fn unescape_char_or_byte(literal_text: &str, mode: Mode) -> Result<char, SpannedError> {
+    let mut chars = literal_text.chars();
+    let res = match chars.next() {
+        None => Err(EscapeError::ZeroChars),
+        Some(first_char) => scan_escape(first_char, &mut chars, mode),
+    };
+    let escape_end = literal_text.len() - chars.as_str().len();
+    let (range, primary_range, error) = match res {
+        Ok(c) if chars.as_str().is_empty() => return Ok(c),
+        Ok(_) => {
+            let primary_range = Some(escape_end..literal_text.len());
+            (0..literal_text.len(), primary_range, EscapeError::MoreThanOneChar)
+        }
+        Err(error) => {
+            (0..escape_end, error.primary_range(literal_text, 0..escape_end, mode), error)
         }
-        _ => return Err(EscapeError::InvalidEscape),
     };
-    Ok(res)
+    Err(SpannedError { range, primary_range, error })
+}
+
+/// Produces a sequence of escaped characters or errors from a contents of a
+/// string literal (without quotes).
+#[derive(Debug, Clone)]
+struct EscapedChars<'a> {
+    initial_len: usize,
+    chars: Chars<'a>,
+    mode: Mode,
 }
 
-fn unescape_char_or_byte(chars: &mut Chars<'_>, mode: Mode) -> Result<char, EscapeError> {
-    let first_char = chars.next().ok_or(EscapeError::ZeroChars)?;
-    let res = scan_escape(first_char, chars, mode)?;
-    if chars.next().is_some() {
-        return Err(EscapeError::MoreThanOneChar);
+impl<'a> EscapedChars<'a> {
+    fn new(src: &'a str, mode: Mode) -> EscapedChars<'a> {
+        assert!(mode.in_double_quotes());
+        EscapedChars { initial_len: src.len(), chars: src.chars(), mode }
+    }
+
+    /// Returns the run of characters which stand for themselves up to the
+    /// next special byte, if there's any.
+    fn next_run(&mut self) -> Option<(Range<usize>, &'a str)> {
+        let rest = self.chars.as_str();
+        // Escapes often come in a row, so don't search for the end of a run
+        // which is empty anyway.
+        if rest.starts_with('\\') {
+            return None;
+        }
+        let run_len = find_special_byte(rest.as_bytes(), self.mode);
+        if run_len == 0 {
+            return None;
+        }
+        // Special bytes are either ASCII, or the first non-ASCII byte after
+        // an ASCII run, so `run_len` is always a char boundary.
+        let (run, rest) = rest.split_at(run_len);
+        let start = self.initial_len - self.chars.as_str().len();
+        self.chars = rest.chars();
+        Some((start..start + run_len, run))
     }
-    Ok(res)
 }
 
-/// Takes a contents of a string literal (without quotes) and produces a
-/// sequence of escaped characters or errors.
-fn unescape_str_or_byte_str<F>(src: &str, mode: Mode, callback: &mut F)
-where
-    F: FnMut(Range<usize>, Result<char, EscapeError>),
-{
-    assert!(mode.in_double_quotes());
-    let initial_len = src.len();
-    let mut chars = src.chars();
-    while let Some(first_char) = chars.next() {
-        let start = initial_len - chars.as_str().len() - first_char.len_utf8();
-
-        let unescaped_char = match first_char {
-            '\\' => {
-                let second_char = chars.clone().next();
-                match second_char {
-                    Some('\n') => {
-                        skip_ascii_whitespace(&mut chars);
+/// Returns the position of the first byte which may not stand for itself in
+/// a string literal in `mode`, or `bytes.len()` if there's none. Looks at
+/// eight bytes at a time.
+fn find_special_byte(bytes: &[u8], mode: Mode) -> usize {
+    const LO: u64 = 0x0101_0101_0101_0101;
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    fn has_zero_byte(word: u64) -> u64 {
+        word.wrapping_sub(LO) & !word & HI
+    }
+    fn has_byte(word: u64, byte: u8) -> u64 {
+        has_zero_byte(word ^ (LO * byte as u64))
+    }
+    fn is_special(byte: u8, mode: Mode) -> bool {
+        match byte {
+            b'\\' | b'\r' | b'"' => true,
+            0 => matches!(mode, Mode::CStr),
+            _ => mode.is_bytes() && !byte.is_ascii(),
+        }
+    }
+
+    let chunks = bytes.chunks_exact(8);
+    let tail_start = bytes.len() - chunks.remainder().len();
+    for (i, chunk) in chunks.enumerate() {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let word = u64::from_le_bytes(word);
+        let mut mask = has_byte(word, b'\\') | has_byte(word, b'\r') | has_byte(word, b'"');
+        if matches!(mode, Mode::CStr) {
+            mask |= has_zero_byte(word);
+        }
+        if mode.is_bytes() {
+            mask |= word & HI;
+        }
+        if mask != 0 {
+            // Only bytes after the first match may be false positives, and
+            // with little-endian words these are the higher bits.
+            return i * 8 + (mask.trailing_zeros() / 8) as usize;
+        }
+    }
+    bytes[tail_start..]
+        .iter()
+        .position(|&b| is_special(b, mode))
+        .map_or(bytes.len(), |pos| tail_start + pos)
+}
+
+impl Iterator for EscapedChars<'_> {
+    type Item = (Range<usize>, Result<char, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let first_char = self.chars.next()?;
+            let start = self.initial_len - self.chars.as_str().len() - first_char.len_utf8();
+
+            let unescaped_char = match first_char {
+                '\\' => {
+                    let rest = self.chars.as_str();
+                    if rest.starts_with('\n') || rest.starts_with("\r\n") {
+                        skip_ascii_whitespace(&mut self.chars);
                         continue;
                     }
-                    _ => scan_escape(first_char, &mut chars, mode),
+                    scan_escape(first_char, &mut self.chars, self.mode)
                 }
-            }
-            '\n' => Ok('\n'),
-            '\t' => Ok('\t'),
-            _ => scan_escape(first_char, &mut chars, mode),
-        };
-        let end = initial_len - chars.as_str().len();
-        callback(start..end, unescaped_char);
+                '\r' if self.chars.as_str().starts_with('\n') => {
+                    self.chars.next();
+                    Ok('\n')
+                }
+                '\n' => Ok('\n'),
+                '\t' => Ok('\t'),
+                _ => scan_escape(first_char, &mut self.chars, self.mode),
+            };
+            let end = self.initial_len - self.chars.as_str().len();
+            return Some((start..end, unescaped_char));
+        }
     }
+}
 
-    fn skip_ascii_whitespace(chars: &mut Chars<'_>) {
-        let str = chars.as_str();
-        let first_non_space = str
-            .bytes()
-            .position(|b| b != b' ' && b != b'\t' && b != b'\n' && b != b'\r')
-            .unwrap_or(str.len());
-        *chars = str[first_non_space..].chars()
-    }
+fn skip_ascii_whitespace(chars: &mut Chars<'_>) {
+    let str = chars.as_str();
+    let first_non_space = str
+        .bytes()
+        .position(|b| b != b' ' && b != b'\t' && b != b'\n' && b != b'\r')
+        .unwrap_or(str.len());
+    *chars = str[first_non_space..].chars()
 }
 
-/// Takes a contents of a string literal (without quotes) and produces a
-/// sequence of characters or errors.
+/// Produces a sequence of characters or errors from a contents of a raw
+/// string literal (without quotes).
 /// NOTE: Raw strings do not perform any explicit character escaping, here we
 /// only translate CRLF to LF and produce errors on bare CR.
-fn unescape_raw_str_or_byte_str<F>(literal_text: &str, mode: Mode, callback: &mut F)
-where
-    F: FnMut(Range<usize>, Result<char, EscapeError>),
-{
-    assert!(mode.in_double_quotes());
-    let initial_len = literal_text.len();
+#[derive(Debug, Clone)]
+struct RawChars<'a> {
+    initial_len: usize,
+    chars: Chars<'a>,
+    mode: Mode,
+}
 
-    let mut chars = literal_text.chars();
-    while let Some(curr) = chars.next() {
-        let start = initial_len - chars.as_str().len() - curr.len_utf8();
+impl<'a> RawChars<'a> {
+    fn new(literal_text: &'a str, mode: Mode) -> RawChars<'a> {
+        assert!(mode.in_double_quotes());
+        RawChars { initial_len: literal_text.len(), chars: literal_text.chars(), mode }
+    }
+}
 
-        let result = if mode.is_bytes() && !curr.is_ascii() {
-            Err(EscapeError::NonAsciiCharInByteString)
-        } else {
-            Ok(curr)
+impl Iterator for RawChars<'_> {
+    type Item = (Range<usize>, Result<char, EscapeError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let curr = self.chars.next()?;
+        let start = self.initial_len - self.chars.as_str().len() - curr.len_utf8();
+
+        let result = match curr {
+            '\r' if self.chars.as_str().starts_with('\n') => {
+                self.chars.next();
+                Ok('\n')
+            }
+            '\r' => Err(EscapeError::BareCarriageReturnInRawString),
+            _ if self.mode.is_bytes() && !curr.is_ascii() => {
+                Err(EscapeError::NonAsciiCharInByteString)
+            }
+            _ => Ok(curr),
         };
-        let end = initial_len - chars.as_str().len();
+        let end = self.initial_len - self.chars.as_str().len();
 
-        callback(start..end, result);
+        Some((start..end, result))
     }
 }
 
+fn mixed_unit_from_char(c: char, is_hex_escape: bool) -> Result<MixedUnit, EscapeError> {
+    if c == '\0' {
+        return Err(EscapeError::NulInCStr);
+    }
+    if is_hex_escape && !c.is_ascii() {
+        return Ok(MixedUnit::HighByte(c as u8));
+    }
+    Ok(MixedUnit::Char(c))
+}
+
 fn byte_from_char(c: char) -> u8 {
     let res = c as u32;
-    assert!(res <= u8::max_value() as u32, "guaranteed because of Mode::Byte(Str)");
+    assert!(res <= u8::MAX as u32, "guaranteed because of Mode::Byte(Str)");
     res as u8
 }
 
@@ -301,10 +1016,27 @@ fn is_ascii(x: u32) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_escape_error_display() {
+        fn check(literal_text: &str, expected_message: &str) {
+            let err = unescape_char(literal_text).unwrap_err();
+            assert_eq!(err.error.to_string(), expected_message);
+        }
+
+        check("", "empty character literal");
+        check(r"\u{0000000}", "unicode escape must have at most 6 hex digits");
+        check(r"\u{D800}", "unicode escape must not be a surrogate");
+        check(r"\x80", "out of range hex escape, must be a character in the range [\\x00-\\x7f]");
+        check("\r", "bare CR not allowed in string, use `\\r` instead");
+
+        let err: Box<dyn std::error::Error> = Box::new(EscapeError::LoneSlash);
+        assert_eq!(err.to_string(), "invalid trailing slash in literal");
+    }
+
     #[test]
     fn test_unescape_char_bad() {
         fn check(literal_text: &str, expected_error: EscapeError) {
-            let actual_result = unescape_char(literal_text).map_err(|(_offset, err)| err);
+            let actual_result = unescape_char(literal_text).map_err(|err| err.error);
             assert_eq!(actual_result, Err(expected_error));
         }
 
@@ -326,7 +1058,9 @@ mod tests {
         check(r"\0a", EscapeError::MoreThanOneChar);
         check(r"\u{0}x", EscapeError::MoreThanOneChar);
         check(r"\u{1F63b}}", EscapeError::MoreThanOneChar);
-        check("\r\n", EscapeError::MoreThanOneChar);
+
+        check("\r", EscapeError::BareCarriageReturn);
+        check("\r\n", EscapeError::BareCarriageReturn);
 
         check(r"\v", EscapeError::InvalidEscape);
         check(r"\💩", EscapeError::InvalidEscape);
@@ -365,6 +1099,46 @@ mod tests {
         check(r"\u{DBFF}", EscapeError::LoneSurrogateUnicodeEscape);
     }
 
+    #[test]
+    fn test_unescape_char_ranges() {
+        fn check(literal_text: &str, range: Range<usize>, primary_range: Option<Range<usize>>) {
+            let err = unescape_char(literal_text).unwrap_err();
+            assert_eq!((err.range, err.primary_range), (range, primary_range));
+        }
+
+        check("", 0..0, None);
+        check("ab", 0..2, Some(1..2));
+        check(r"\u{41}bc", 0..8, Some(6..8));
+        check(r"\", 0..1, None);
+        check(r"\v", 0..2, Some(1..2));
+        check(r"\💩", 0..5, Some(1..5));
+        check("\n", 0..1, None);
+        check(r"\x", 0..2, None);
+        check(r"\x0", 0..3, None);
+        check(r"\xtt", 0..3, Some(2..3));
+        check(r"\x🦀", 0..6, Some(2..6));
+        check(r"\xff", 0..4, Some(2..4));
+        check(r"\u", 0..2, None);
+        check(r"\u[0123]", 0..3, Some(2..3));
+        check(r"\u{0x}", 0..5, Some(4..5));
+        check(r"\u{0000", 0..7, None);
+        check(r"\u{}", 0..4, None);
+        check(r"\u{__0}", 0..4, Some(3..4));
+        check(r"\u{0000000}", 0..11, Some(9..10));
+        check(r"\u{00_0000_00}", 0..14, Some(11..13));
+        check(r"\u{FFFFFF}", 0..10, Some(3..9));
+        check(r"\u{D800}", 0..8, Some(3..7));
+        check("\r", 0..1, None);
+
+        let err = EscapeError::OverlongUnicodeEscape;
+        assert_eq!(err.primary_range(r"ab\u{0000000}c", 2..13, Mode::Str), Some(11..12));
+
+        let err = unescape_byte(r"\xffa").unwrap_err();
+        assert_eq!((err.range, err.primary_range), (0..5, Some(4..5)));
+        let err = EscapeError::MoreThanOneChar;
+        assert_eq!(err.primary_range(r"\xffa", 0..5, Mode::Byte), Some(4..5));
+    }
+
     #[test]
     fn test_unescape_char_good() {
         fn check(literal_text: &str, expected_char: char) {
@@ -375,7 +1149,6 @@ mod tests {
         check("a", 'a');
         check("ы", 'ы');
         check("🦀", '🦀');
-        check("\r", '\r');
 
         check(r#"\""#, '"');
         check(r"\n", '\n');
@@ -420,13 +1193,28 @@ mod tests {
         check(" \t\n", " \t\n");
 
         check("hello \\\n     world", "hello world");
-        check("thread's", "thread's")
+        check("hello \\\r\n     world", "hello world");
+        check("thread's", "thread's");
+        check("a\r\nb", "a\nb");
+    }
+
+    #[test]
+    fn test_unescape_str_bad() {
+        fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
+            let mut unescaped = Vec::with_capacity(literal.len());
+            unescape_str(literal, &mut |range, res| unescaped.push((range, res)));
+            assert_eq!(unescaped, expected);
+        }
+
+        check("\r", &[(0..1, Err(EscapeError::BareCarriageReturn))]);
+        check("\rx", &[(0..1, Err(EscapeError::BareCarriageReturn)), (1..2, Ok('x'))]);
+        check("\r\r\n", &[(0..1, Err(EscapeError::BareCarriageReturn)), (1..3, Ok('\n'))]);
     }
 
     #[test]
     fn test_unescape_byte_bad() {
         fn check(literal_text: &str, expected_error: EscapeError) {
-            let actual_result = unescape_byte(literal_text).map_err(|(_offset, err)| err);
+            let actual_result = unescape_byte(literal_text).map_err(|err| err.error);
             assert_eq!(actual_result, Err(expected_error));
         }
 
@@ -446,7 +1234,9 @@ mod tests {
         check(r"\\a", EscapeError::MoreThanOneChar);
         check(r"\'a", EscapeError::MoreThanOneChar);
         check(r"\0a", EscapeError::MoreThanOneChar);
-        check("\r\n", EscapeError::MoreThanOneChar);
+
+        check("\r", EscapeError::BareCarriageReturn);
+        check("\r\n", EscapeError::BareCarriageReturn);
 
         check(r"\v", EscapeError::InvalidEscape);
         check(r"\💩", EscapeError::InvalidEscape);
@@ -501,7 +1291,6 @@ mod tests {
         }
 
         check("a", b'a');
-        check("\r", b'\r');
 
         check(r#"\""#, b'"');
         check(r"\n", b'\n');
@@ -544,6 +1333,164 @@ mod tests {
         check("thread's", b"thread's")
     }
 
+    #[test]
+    fn test_unescape_str_iter() {
+        let mut iter = UnescapeStr::new(r"ab\qc");
+        assert_eq!(iter.next(), Some((0..1, Ok('a'))));
+        let prefix: String = iter.by_ref().map_while(|(_, c)| c.ok()).collect();
+        assert_eq!(prefix, "b");
+        assert_eq!(iter.next(), Some((4..5, Ok('c'))));
+        assert_eq!(iter.next(), None);
+
+        let bytes: Result<Vec<u8>, _> = UnescapeByteStr::new(r"a\xff").map(|(_, b)| b).collect();
+        assert_eq!(bytes, Ok(vec![b'a', 0xff]));
+        let bytes: Result<Vec<u8>, _> = UnescapeRawByteStr::new("aы").map(|(_, b)| b).collect();
+        assert_eq!(bytes, Err(EscapeError::NonAsciiCharInByteString));
+
+        let raw: Vec<_> = UnescapeRawStr::new(r"\n").collect();
+        assert_eq!(raw, [(0..1, Ok('\\')), (1..2, Ok('n'))]);
+
+        let mut buf = Vec::new();
+        for (_, unit) in UnescapeCStr::new(r"\xffы") {
+            unit.unwrap().push_to(&mut buf);
+        }
+        assert_eq!(buf, b"\xff\xd1\x8b");
+        let units: Vec<_> = UnescapeRawCStr::new("\0").collect();
+        assert_eq!(units, [(0..1, Err(EscapeError::NulInCStr))]);
+    }
+
+    #[test]
+    fn test_unescape_cow() {
+        let cow = unescape_str_cow("hello, ы").unwrap();
+        assert!(matches!(cow, Cow::Borrowed(_)));
+        assert_eq!(cow, "hello, ы");
+        let cow = unescape_str_cow(r"hello\n").unwrap();
+        assert!(matches!(cow, Cow::Owned(_)));
+        assert_eq!(cow, "hello\n");
+        let cow = unescape_str_cow("hello \\\n world").unwrap();
+        assert_eq!(cow, "hello world");
+        assert_eq!(
+            unescape_str_cow(r#"a"b\q\u{}"#),
+            Err(vec![
+                (1..2, EscapeError::EscapeOnlyChar),
+                (3..5, EscapeError::InvalidEscape),
+                (5..9, EscapeError::EmptyUnicodeEscape),
+            ])
+        );
+
+        let cow = unescape_byte_str_cow("foo").unwrap();
+        assert!(matches!(cow, Cow::Borrowed(_)));
+        assert_eq!(unescape_byte_str_cow(r"\xff").unwrap(), &b"\xff"[..]);
+        assert_eq!(unescape_byte_str_cow("ы"), Err(vec![(0..2, EscapeError::NonAsciiCharInByte)]));
+
+        let cow = unescape_raw_str_cow(r"\n").unwrap();
+        assert!(matches!(cow, Cow::Borrowed(_)));
+        assert_eq!(cow, r"\n");
+        assert_eq!(unescape_raw_str_cow("a\r\n").unwrap(), "a\n");
+        let cow = unescape_raw_byte_str_cow(r"\x").unwrap();
+        assert!(matches!(cow, Cow::Borrowed(_)));
+        assert_eq!(
+            unescape_raw_byte_str_cow("ы"),
+            Err(vec![(0..2, EscapeError::NonAsciiCharInByteString)])
+        );
+
+        let cow = unescape_c_str_cow("ы").unwrap();
+        assert!(matches!(cow, Cow::Borrowed(_)));
+        assert_eq!(unescape_c_str_cow(r"\xff\u{44b}").unwrap(), &b"\xff\xd1\x8b"[..]);
+        assert_eq!(unescape_c_str_cow(r"\0"), Err(vec![(0..2, EscapeError::NulInCStr)]));
+        assert_eq!(unescape_raw_c_str_cow("\0"), Err(vec![(0..1, EscapeError::NulInCStr)]));
+    }
+
+    #[test]
+    fn test_unescape_str_chunks() {
+        fn check(literal_text: &str, expected: &[<UnescapeStrChunks<'_> as Iterator>::Item]) {
+            let actual: Vec<_> = UnescapeStrChunks::new(literal_text).collect();
+            assert_eq!(actual, expected);
+
+            let mut from_chunks = Vec::new();
+            for (range, res) in UnescapeStrChunks::new(literal_text) {
+                match res {
+                    Ok(Chunk::Run(run)) => {
+                        let mut start = range.start;
+                        for c in run.chars() {
+                            from_chunks.push((start..start + c.len_utf8(), Ok(c)));
+                            start += c.len_utf8();
+                        }
+                    }
+                    Ok(Chunk::Unit(c)) => from_chunks.push((range, Ok(c))),
+                    Err(err) => from_chunks.push((range, Err(err))),
+                }
+            }
+            let from_chars: Vec<_> = UnescapeStr::new(literal_text).collect();
+            assert_eq!(from_chunks, from_chars);
+        }
+
+        check("", &[]);
+        check("hello, мир\n\t!", &[(0..16, Ok(Chunk::Run("hello, мир\n\t!")))]);
+        check(
+            r"abcdefghijklmno\npqr",
+            &[
+                (0..15, Ok(Chunk::Run("abcdefghijklmno"))),
+                (15..17, Ok(Chunk::Unit('\n'))),
+                (17..20, Ok(Chunk::Run("pqr"))),
+            ],
+        );
+        check(
+            "abcdefgh\\\n   ijklmnop\"",
+            &[
+                (0..8, Ok(Chunk::Run("abcdefgh"))),
+                (13..14, Ok(Chunk::Unit('i'))),
+                (14..21, Ok(Chunk::Run("jklmnop"))),
+                (21..22, Err(EscapeError::EscapeOnlyChar)),
+            ],
+        );
+        check("0123456\r\n", &[(0..7, Ok(Chunk::Run("0123456"))), (7..9, Ok(Chunk::Unit('\n')))]);
+    }
+
+    #[test]
+    fn test_unescape_byte_str_chunks() {
+        fn check(literal_text: &str, expected: &[<UnescapeByteStrChunks<'_> as Iterator>::Item]) {
+            let actual: Vec<_> = UnescapeByteStrChunks::new(literal_text).collect();
+            assert_eq!(actual, expected);
+        }
+
+        check("hello\tworld", &[(0..11, Ok(Chunk::Run("hello\tworld")))]);
+        check(
+            r"0123456789\xff",
+            &[(0..10, Ok(Chunk::Run("0123456789"))), (10..14, Ok(Chunk::Unit(0xff)))],
+        );
+        check(
+            "0123456789ы",
+            &[
+                (0..10, Ok(Chunk::Run("0123456789"))),
+                (10..12, Err(EscapeError::NonAsciiCharInByte)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_find_special_byte() {
+        fn check(text: &str, mode: Mode, expected: usize) {
+            assert_eq!(find_special_byte(text.as_bytes(), mode), expected);
+        }
+
+        check("", Mode::Str, 0);
+        check("abc", Mode::Str, 3);
+        check("abcdefgh", Mode::Str, 8);
+        check("abcdefghijklmnopqrstuvwxyz", Mode::Str, 26);
+        for i in 0..20 {
+            let prefix = "x".repeat(i);
+            check(&format!("{}\\{}", prefix, prefix), Mode::Str, i);
+            check(&format!("{}\"\\", prefix), Mode::Str, i);
+            check(&format!("{}\r\\", prefix), Mode::Str, i);
+            check(&format!("{}ы\\", prefix), Mode::Str, i + 2);
+            check(&format!("{}ы\\", prefix), Mode::ByteStr, i);
+            check(&format!("{}\0\\", prefix), Mode::Str, i + 1);
+            check(&format!("{}\0\\", prefix), Mode::CStr, i);
+            check(&format!("{}\x01\x7f\x21", prefix), Mode::Str, i + 3);
+        }
+    }
+
     #[test]
     fn test_unescape_raw_str() {
         fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
@@ -552,9 +1499,9 @@ mod tests {
             assert_eq!(unescaped, expected);
         }
 
-        check("\r\n", &[(0..1, Ok('\r')), (1..2, Ok('\n'))]);
-        check("\r", &[(0..1, Ok('\r'))]);
-        check("\rx", &[(0..1, Ok('\r')), (1..2, Ok('x'))]);
+        check("\r\n", &[(0..2, Ok('\n'))]);
+        check("\r", &[(0..1, Err(EscapeError::BareCarriageReturnInRawString))]);
+        check("\rx", &[(0..1, Err(EscapeError::BareCarriageReturnInRawString)), (1..2, Ok('x'))]);
     }
 
     #[test]
@@ -565,12 +1512,70 @@ mod tests {
             assert_eq!(unescaped, expected);
         }
 
-        check("\r\n", &[(0..1, Ok(b'\r')), (1..2, Ok(b'\n'))]);
-        check("\r", &[(0..1, Ok(b'\r'))]);
+        check("\r\n", &[(0..2, Ok(b'\n'))]);
+        check("\r", &[(0..1, Err(EscapeError::BareCarriageReturnInRawString))]);
         check("🦀", &[(0..4, Err(EscapeError::NonAsciiCharInByteString))]);
         check(
             "🦀a",
             &[(0..4, Err(EscapeError::NonAsciiCharInByteString)), (4..5, Ok(byte_from_char('a')))],
         );
     }
+
+    #[test]
+    fn test_unescape_c_str_good() {
+        fn check(literal_text: &str, expected: &[u8]) {
+            let mut buf = Ok(Vec::with_capacity(literal_text.len()));
+            unescape_c_str(literal_text, &mut |range, c| {
+                if let Ok(b) = &mut buf {
+                    match c {
+                        Ok(c) => c.push_to(b),
+                        Err(e) => buf = Err((range, e)),
+                    }
+                }
+            });
+            let buf = buf.as_ref().map(|it| it.as_ref());
+            assert_eq!(buf, Ok(expected))
+        }
+
+        check("foo", b"foo");
+        check("", b"");
+        check("hello \\\n     world", b"hello world");
+        check("ы🦀", "ы🦀".as_bytes());
+        check(r"\x7f\x80\xff", b"\x7f\x80\xff");
+        check(r"\u{80}\u{ff}", "\u{80}\u{ff}".as_bytes());
+        check(r"\u{1F63b}", "😻".as_bytes());
+        check(r"\xd1\x8b", "ы".as_bytes());
+    }
+
+    #[test]
+    fn test_unescape_c_str_bad() {
+        fn check(literal_text: &str, expected: &[(Range<usize>, Result<MixedUnit, EscapeError>)]) {
+            let mut unescaped = Vec::with_capacity(literal_text.len());
+            unescape_c_str(literal_text, &mut |range, res| unescaped.push((range, res)));
+            assert_eq!(unescaped, expected);
+        }
+
+        check("\0", &[(0..1, Err(EscapeError::NulInCStr))]);
+        check(r"\0", &[(0..2, Err(EscapeError::NulInCStr))]);
+        check(r"\x00", &[(0..4, Err(EscapeError::NulInCStr))]);
+        check(r"\u{0}", &[(0..5, Err(EscapeError::NulInCStr))]);
+        check(
+            r"a\u{000}",
+            &[(0..1, Ok(MixedUnit::Char('a'))), (1..8, Err(EscapeError::NulInCStr))],
+        );
+        check(r"\u{110000}", &[(0..10, Err(EscapeError::OutOfRangeUnicodeEscape))]);
+    }
+
+    #[test]
+    fn test_unescape_raw_c_str() {
+        fn check(literal: &str, expected: &[(Range<usize>, Result<MixedUnit, EscapeError>)]) {
+            let mut unescaped = Vec::with_capacity(literal.len());
+            unescape_raw_c_str(literal, &mut |range, res| unescaped.push((range, res)));
+            assert_eq!(unescaped, expected);
+        }
+
+        check(r"\x", &[(0..1, Ok(MixedUnit::Char('\\'))), (1..2, Ok(MixedUnit::Char('x')))]);
+        check("ы", &[(0..2, Ok(MixedUnit::Char('ы')))]);
+        check("\0", &[(0..1, Err(EscapeError::NulInCStr))]);
+    }
 }
@@ -0,0 +1,423 @@
+//! Working with whole literal tokens: quotes, `b` and `r` prefixes, raw string
+//! hashes and suffixes, on top of the `unescape_*` functions.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{
+    EscapeError, Help, Mode, MixedUnit, RawStrError, lex_raw_str, unescape_byte, unescape_byte_str,
+    unescape_c_str, unescape_char, unescape_raw_byte_str, unescape_raw_c_str, unescape_raw_str,
+    unescape_str,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralKind {
+    Char,
+    Byte,
+    Str,
+    ByteStr,
+    CStr,
+    RawStr { n_hashes: usize },
+    RawByteStr { n_hashes: usize },
+    RawCStr { n_hashes: usize },
+}
+
+impl LiteralKind {
+    pub fn mode(self) -> Mode {
+        match self {
+            LiteralKind::Char => Mode::Char,
+            LiteralKind::Byte => Mode::Byte,
+            LiteralKind::Str | LiteralKind::RawStr { .. } => Mode::Str,
+            LiteralKind::ByteStr | LiteralKind::RawByteStr { .. } => Mode::ByteStr,
+            LiteralKind::CStr | LiteralKind::RawCStr { .. } => Mode::CStr,
+        }
+    }
+
+    pub fn is_raw(self) -> bool {
+        match self {
+            LiteralKind::RawStr { .. }
+            | LiteralKind::RawByteStr { .. }
+            | LiteralKind::RawCStr { .. } => true,
+            LiteralKind::Char
+            | LiteralKind::Byte
+            | LiteralKind::Str
+            | LiteralKind::ByteStr
+            | LiteralKind::CStr => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiteralValue {
+    Char(char),
+    Byte(u8),
+    Str(String),
+    ByteStr(Vec<u8>),
+    /// Bytes of a C string, without the terminating NUL.
+    CStr(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Literal<'a> {
+    pub kind: LiteralKind,
+    pub value: LiteralValue,
+    pub suffix: &'a str,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LiteralError {
+    NotALiteral,
+    UnterminatedLiteral,
+    /// Text after the closing quote which is not an identifier.
+    InvalidSuffix,
+    RawStr(RawStrError),
+    Escape(EscapeError),
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiteralError::NotALiteral => f.write_str("expected a char, byte or string literal"),
+            LiteralError::UnterminatedLiteral => f.write_str("unterminated literal"),
+            LiteralError::InvalidSuffix => f.write_str("suffix of a literal must be an identifier"),
+            LiteralError::RawStr(err) => err.fmt(f),
+            LiteralError::Escape(err) => err.fmt(f),
+        }
+    }
+}
+
+impl LiteralError {
+    /// Returns a help for this error, found at `range` of `token_text`. Ranges
+    /// of suggestions are relative to `token_text` as well.
+    pub fn help(&self, token_text: &str, range: Range<usize>) -> Option<Help> {
+        if let LiteralError::RawStr(RawStrError::NoTerminator {
+            expected,
+            found,
+            possible_terminator_offset: Some(offset),
+        }) = *self
+        {
+            let end = offset + 1 + found;
+            return Some(Help {
+                message: "consider terminating the string here".to_string(),
+                suggestion: Some((end..end, "#".repeat(expected - found))),
+            });
+        }
+        let err = match self {
+            LiteralError::Escape(err) => err,
+            _ => return None,
+        };
+        let (kind, contents) = lex_literal(token_text).ok()?;
+        let literal_text = &token_text[contents.clone()];
+
+        if let (EscapeError::MoreThanOneChar, LiteralKind::Char | LiteralKind::Byte) = (err, kind) {
+            let prefix = if kind.mode().is_bytes() { "b" } else { "" };
+            let mut replacement = format!("{}\"", prefix);
+            let mut chars = literal_text.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        replacement.push(c);
+                        replacement.extend(chars.next());
+                    }
+                    '"' => replacement.push_str("\\\""),
+                    _ => replacement.push(c),
+                }
+            }
+            replacement.push('"');
+            return Some(Help {
+                message: "if you meant to write a string literal, use double quotes".to_string(),
+                suggestion: Some((0..contents.end + 1, replacement)),
+            });
+        }
+
+        let shift = contents.start;
+        let mut help =
+            err.help(literal_text, range.start - shift..range.end - shift, kind.mode())?;
+        if let Some((range, _)) = &mut help.suggestion {
+            *range = range.start + shift..range.end + shift;
+        }
+        Some(help)
+    }
+}
+
+impl std::error::Error for LiteralError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LiteralError::RawStr(err) => Some(err),
+            LiteralError::Escape(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Takes a text of a whole char, byte or string literal token (with prefix,
+/// quotes and suffix), and returns its value or all errors in it. Ranges of
+/// errors are relative to the start of the token.
+pub fn unescape_literal(
+    token_text: &str,
+) -> Result<Literal<'_>, Vec<(Range<usize>, LiteralError)>> {
+    let (kind, contents) = lex_literal(token_text).map_err(|err| vec![err])?;
+    let literal_text = &token_text[contents.clone()];
+    let suffix_start = contents.end + closing_len(kind);
+    let suffix = &token_text[suffix_start..];
+
+    let mut errors = Vec::new();
+    let shift = |range: Range<usize>| range.start + contents.start..range.end + contents.start;
+    let value = match kind {
+        LiteralKind::Char => match unescape_char(literal_text) {
+            Ok(c) => LiteralValue::Char(c),
+            Err(err) => {
+                errors.push((shift(err.range), LiteralError::Escape(err.error)));
+                LiteralValue::Char('\0')
+            }
+        },
+        LiteralKind::Byte => match unescape_byte(literal_text) {
+            Ok(b) => LiteralValue::Byte(b),
+            Err(err) => {
+                errors.push((shift(err.range), LiteralError::Escape(err.error)));
+                LiteralValue::Byte(0)
+            }
+        },
+        LiteralKind::Str | LiteralKind::RawStr { .. } => {
+            let mut buf = String::with_capacity(literal_text.len());
+            let mut callback = |range: Range<usize>, c| match c {
+                Ok(c) => buf.push(c),
+                Err(err) => errors.push((shift(range), LiteralError::Escape(err))),
+            };
+            if kind.is_raw() {
+                unescape_raw_str(literal_text, &mut callback)
+            } else {
+                unescape_str(literal_text, &mut callback)
+            }
+            LiteralValue::Str(buf)
+        }
+        LiteralKind::ByteStr | LiteralKind::RawByteStr { .. } => {
+            let mut buf = Vec::with_capacity(literal_text.len());
+            let mut callback = |range: Range<usize>, b| match b {
+                Ok(b) => buf.push(b),
+                Err(err) => errors.push((shift(range), LiteralError::Escape(err))),
+            };
+            if kind.is_raw() {
+                unescape_raw_byte_str(literal_text, &mut callback)
+            } else {
+                unescape_byte_str(literal_text, &mut callback)
+            }
+            LiteralValue::ByteStr(buf)
+        }
+        LiteralKind::CStr | LiteralKind::RawCStr { .. } => {
+            let mut buf = Vec::with_capacity(literal_text.len());
+            let mut callback = |range: Range<usize>, unit: Result<MixedUnit, _>| match unit {
+                Ok(unit) => unit.push_to(&mut buf),
+                Err(err) => errors.push((shift(range), LiteralError::Escape(err))),
+            };
+            if kind.is_raw() {
+                unescape_raw_c_str(literal_text, &mut callback)
+            } else {
+                unescape_c_str(literal_text, &mut callback)
+            }
+            LiteralValue::CStr(buf)
+        }
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(Literal { kind, value, suffix })
+}
+
+/// Splits a literal token into its kind and the range of its contents, that
+/// is, the text between the quotes.
+fn lex_literal(
+    token_text: &str,
+) -> Result<(LiteralKind, Range<usize>), (Range<usize>, LiteralError)> {
+    let (kind, contents) = lex_quotes(token_text)?;
+    let suffix_start = contents.end + closing_len(kind);
+    if !is_suffix(&token_text[suffix_start..]) {
+        return Err((suffix_start..token_text.len(), LiteralError::InvalidSuffix));
+    }
+    Ok((kind, contents))
+}
+
+fn lex_quotes(
+    token_text: &str,
+) -> Result<(LiteralKind, Range<usize>), (Range<usize>, LiteralError)> {
+    let is_raw = ["r", "br", "cr"].iter().any(|prefix| token_text.starts_with(prefix));
+    if is_raw {
+        return lex_raw_str(token_text);
+    }
+    let (kind, prefix_len) = if token_text.starts_with("b'") {
+        (LiteralKind::Byte, 1)
+    } else if token_text.starts_with("b\"") {
+        (LiteralKind::ByteStr, 1)
+    } else if token_text.starts_with("c\"") {
+        (LiteralKind::CStr, 1)
+    } else if token_text.starts_with('\'') {
+        (LiteralKind::Char, 0)
+    } else if token_text.starts_with('"') {
+        (LiteralKind::Str, 0)
+    } else {
+        return Err((0..token_text.len(), LiteralError::NotALiteral));
+    };
+
+    let quote = if kind.mode().in_single_quotes() { '\'' } else { '"' };
+    let start = prefix_len + 1;
+    // Like rustc, take a quote followed by another one as the only char of
+    // the literal, so that `'''` is reported as an unescaped quote.
+    if kind.mode().in_single_quotes() && token_text[start..].starts_with("''") {
+        return Ok((kind, start..start + 1));
+    }
+    let mut chars = token_text[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return Ok((kind, start..start + i));
+        }
+    }
+    Err((0..token_text.len(), LiteralError::UnterminatedLiteral))
+}
+
+/// Whether `suffix` is empty or an identifier.
+fn is_suffix(suffix: &str) -> bool {
+    let mut chars = suffix.chars();
+    match chars.next() {
+        None => true,
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        Some(_) => false,
+    }
+}
+
+fn closing_len(kind: LiteralKind) -> usize {
+    match kind {
+        LiteralKind::RawStr { n_hashes }
+        | LiteralKind::RawByteStr { n_hashes }
+        | LiteralKind::RawCStr { n_hashes } => 1 + n_hashes,
+        LiteralKind::Char
+        | LiteralKind::Byte
+        | LiteralKind::Str
+        | LiteralKind::ByteStr
+        | LiteralKind::CStr => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_literal_good() {
+        fn check(token_text: &str, kind: LiteralKind, value: LiteralValue, suffix: &str) {
+            let actual = unescape_literal(token_text);
+            assert_eq!(actual, Ok(Literal { kind, value, suffix }));
+        }
+
+        check("'a'", LiteralKind::Char, LiteralValue::Char('a'), "");
+        check(r"'\''", LiteralKind::Char, LiteralValue::Char('\''), "");
+        check(r"'\u{1F63b}'x", LiteralKind::Char, LiteralValue::Char('😻'), "x");
+        check("b'x'", LiteralKind::Byte, LiteralValue::Byte(b'x'), "");
+        check(r"b'\xff'u8", LiteralKind::Byte, LiteralValue::Byte(0xff), "u8");
+        check(r#""abc""#, LiteralKind::Str, LiteralValue::Str("abc".to_string()), "");
+        check(r#""abc"suffix"#, LiteralKind::Str, LiteralValue::Str("abc".to_string()), "suffix");
+        check(r#""a\"b""#, LiteralKind::Str, LiteralValue::Str("a\"b".to_string()), "");
+        check(r#"b"a\xffb""#, LiteralKind::ByteStr, LiteralValue::ByteStr(b"a\xffb".to_vec()), "");
+        check(
+            r#"r"a\nb""#,
+            LiteralKind::RawStr { n_hashes: 0 },
+            LiteralValue::Str(r"a\nb".to_string()),
+            "",
+        );
+        check(
+            r###"br##"a"#b"##"###,
+            LiteralKind::RawByteStr { n_hashes: 2 },
+            LiteralValue::ByteStr(br##"a"#b"##.to_vec()),
+            "",
+        );
+        check(
+            r##"r#"x"#_y"##,
+            LiteralKind::RawStr { n_hashes: 1 },
+            LiteralValue::Str("x".to_string()),
+            "_y",
+        );
+        check(
+            r#"c"a\xffы\u{1F63b}""#,
+            LiteralKind::CStr,
+            LiteralValue::CStr(b"a\xff\xd1\x8b\xf0\x9f\x98\xbb".to_vec()),
+            "",
+        );
+        check(
+            r##"cr#"\x"#"##,
+            LiteralKind::RawCStr { n_hashes: 1 },
+            LiteralValue::CStr(br"\x".to_vec()),
+            "",
+        );
+    }
+
+    #[test]
+    fn test_unescape_literal_bad() {
+        fn check(token_text: &str, expected: &[(Range<usize>, LiteralError)]) {
+            let actual = unescape_literal(token_text);
+            assert_eq!(actual.as_ref().map_err(|it| it.as_slice()), Err(expected));
+        }
+
+        check("abc", &[(0..3, LiteralError::NotALiteral)]);
+        check("'a", &[(0..2, LiteralError::UnterminatedLiteral)]);
+        check(r#""a\""#, &[(0..4, LiteralError::UnterminatedLiteral)]);
+        check("'a'b'", &[(3..5, LiteralError::InvalidSuffix)]);
+        check(r#""a""b""#, &[(3..6, LiteralError::InvalidSuffix)]);
+        check(r###"r#"a"##"###, &[(6..7, LiteralError::InvalidSuffix)]);
+        check(r#""a"1"#, &[(3..4, LiteralError::InvalidSuffix)]);
+        check(
+            r###"r##"a"#"###,
+            &[(
+                0..4,
+                LiteralError::RawStr(RawStrError::NoTerminator {
+                    expected: 2,
+                    found: 1,
+                    possible_terminator_offset: Some(5),
+                }),
+            )],
+        );
+        check(
+            "r#a",
+            &[(2..3, LiteralError::RawStr(RawStrError::InvalidStarter { bad_char: 'a' }))],
+        );
+        check("''", &[(1..1, LiteralError::Escape(EscapeError::ZeroChars))]);
+        check("'''", &[(1..2, LiteralError::Escape(EscapeError::EscapeOnlyChar))]);
+        check("b'''", &[(2..3, LiteralError::Escape(EscapeError::EscapeOnlyChar))]);
+        check("''''", &[(3..4, LiteralError::InvalidSuffix)]);
+        check("'ab'", &[(1..3, LiteralError::Escape(EscapeError::MoreThanOneChar))]);
+        check(r"'\x0ff'", &[(1..6, LiteralError::Escape(EscapeError::MoreThanOneChar))]);
+        check(r"'\xtt'", &[(1..4, LiteralError::Escape(EscapeError::InvalidCharInHexEscape))]);
+        check(
+            r#""a\qb\xffc""#,
+            &[
+                (2..4, LiteralError::Escape(EscapeError::InvalidEscape)),
+                (5..9, LiteralError::Escape(EscapeError::OutOfRangeHexEscape)),
+            ],
+        );
+        check(r#"br"ы""#, &[(3..5, LiteralError::Escape(EscapeError::NonAsciiCharInByteString))]);
+        check(r#"c"a\0""#, &[(3..5, LiteralError::Escape(EscapeError::NulInCStr))]);
+    }
+
+    #[test]
+    fn test_literal_error_help() {
+        fn check(token_text: &str, expected_fix: &str) {
+            let errors = unescape_literal(token_text).unwrap_err();
+            let (range, err) = &errors[0];
+            let (range, replacement) =
+                err.help(token_text, range.clone()).unwrap().suggestion.unwrap();
+            let mut fixed = token_text.to_string();
+            fixed.replace_range(range, &replacement);
+            assert_eq!(fixed, expected_fix);
+        }
+
+        check("'ab'", r#""ab""#);
+        check(r#"'a"\''x"#, r#""a\"\'"x"#);
+        check("b'ab'", r#"b"ab""#);
+        check(r#""a\qb""#, r#""a\\qb""#);
+        check(r#"b"ы""#, r#"b"\xD1\x8B""#);
+        check(r"'\u1234'", r"'\u{1234}'");
+        check("'''", r"'\''");
+        check(r####"r###"a"##b"####, r####"r###"a"###b"####);
+    }
+}
@@ -0,0 +1,164 @@
+//! Unescaping which never fails: each malformed escape or character is
+//! replaced, and errors are returned on the side.
+
+use std::char::REPLACEMENT_CHARACTER;
+use std::ops::Range;
+
+use crate::{
+    Chunk, EscapeError, EscapeErrors, MixedUnit, UnescapeByteStrChunks, UnescapeCStr,
+    UnescapeRawByteStr, UnescapeRawCStr, UnescapeRawStr, UnescapeStrChunks, unescape_byte,
+    unescape_char,
+};
+
+/// Unescapes a contents of a char literal (without quotes), replacing an
+/// invalid one with U+FFFD.
+pub fn unescape_char_lossy(literal_text: &str) -> (char, EscapeErrors) {
+    match unescape_char(literal_text) {
+        Ok(c) => (c, Vec::new()),
+        Err(err) => (REPLACEMENT_CHARACTER, vec![(err.range, err.error)]),
+    }
+}
+
+/// Unescapes a contents of a byte literal (without quotes), replacing an
+/// invalid one with `replacement`.
+pub fn unescape_byte_lossy(literal_text: &str, replacement: u8) -> (u8, EscapeErrors) {
+    match unescape_byte(literal_text) {
+        Ok(b) => (b, Vec::new()),
+        Err(err) => (replacement, vec![(err.range, err.error)]),
+    }
+}
+
+/// Unescapes a contents of a string literal (without quotes) into a string,
+/// replacing each malformed escape or character with U+FFFD.
+pub fn unescape_str_lossy(literal_text: &str) -> (String, EscapeErrors) {
+    let chunks = UnescapeStrChunks::new(literal_text);
+    let replacement = Chunk::Unit(REPLACEMENT_CHARACTER);
+    collect_lossy(chunks, replacement, |buf: &mut String, chunk| match chunk {
+        Chunk::Run(run) => buf.push_str(run),
+        Chunk::Unit(c) => buf.push(c),
+    })
+}
+
+/// Unescapes a contents of a byte string literal (without quotes) into bytes,
+/// replacing each malformed escape or character with `replacement`.
+pub fn unescape_byte_str_lossy(literal_text: &str, replacement: u8) -> (Vec<u8>, EscapeErrors) {
+    let chunks = UnescapeByteStrChunks::new(literal_text);
+    collect_lossy(chunks, Chunk::Unit(replacement), |buf: &mut Vec<u8>, chunk| match chunk {
+        Chunk::Run(run) => buf.extend_from_slice(run.as_bytes()),
+        Chunk::Unit(b) => buf.push(b),
+    })
+}
+
+pub fn unescape_raw_str_lossy(literal_text: &str) -> (String, EscapeErrors) {
+    collect_lossy(UnescapeRawStr::new(literal_text), REPLACEMENT_CHARACTER, String::push)
+}
+
+pub fn unescape_raw_byte_str_lossy(literal_text: &str, replacement: u8) -> (Vec<u8>, EscapeErrors) {
+    collect_lossy(UnescapeRawByteStr::new(literal_text), replacement, Vec::push)
+}
+
+/// Unescapes a contents of a C string literal (without quotes) into bytes,
+/// without the terminating NUL. Malformed escapes and characters, including
+/// NULs, are replaced with the UTF-8 encoding of U+FFFD.
+pub fn unescape_c_str_lossy(literal_text: &str) -> (Vec<u8>, EscapeErrors) {
+    let replacement = MixedUnit::Char(REPLACEMENT_CHARACTER);
+    collect_lossy(UnescapeCStr::new(literal_text), replacement, |buf, unit| unit.push_to(buf))
+}
+
+pub fn unescape_raw_c_str_lossy(literal_text: &str) -> (Vec<u8>, EscapeErrors) {
+    let replacement = MixedUnit::Char(REPLACEMENT_CHARACTER);
+    collect_lossy(UnescapeRawCStr::new(literal_text), replacement, |buf, unit| unit.push_to(buf))
+}
+
+fn collect_lossy<B: Default, T: Copy>(
+    units: impl Iterator<Item = (Range<usize>, Result<T, EscapeError>)>,
+    replacement: T,
+    push: impl Fn(&mut B, T),
+) -> (B, EscapeErrors) {
+    let mut buf = B::default();
+    let mut errors = Vec::new();
+    for (range, unit) in units {
+        match unit {
+            Ok(unit) => push(&mut buf, unit),
+            Err(err) => {
+                push(&mut buf, replacement);
+                errors.push((range, err));
+            }
+        }
+    }
+    (buf, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unescape_str_cow;
+
+    #[test]
+    fn test_unescape_str_lossy() {
+        fn check(
+            literal_text: &str,
+            expected: &str,
+            expected_errors: &[(Range<usize>, EscapeError)],
+        ) {
+            let (actual, errors) = unescape_str_lossy(literal_text);
+            assert_eq!((actual.as_str(), errors.as_slice()), (expected, expected_errors));
+            if errors.is_empty() {
+                assert_eq!(unescape_str_cow(literal_text).unwrap(), expected);
+            }
+        }
+
+        check("", "", &[]);
+        check(r"a\nb\u{1F63b}", "a\nb😻", &[]);
+        check(
+            r"a\qb\x80c\u{D800}",
+            "a\u{FFFD}b\u{FFFD}c\u{FFFD}",
+            &[
+                (1..3, EscapeError::InvalidEscape),
+                (4..8, EscapeError::OutOfRangeHexEscape),
+                (9..17, EscapeError::LoneSurrogateUnicodeEscape),
+            ],
+        );
+        check(
+            "a\"b\rc\r\n",
+            "a\u{FFFD}b\u{FFFD}c\n",
+            &[(1..2, EscapeError::EscapeOnlyChar), (3..4, EscapeError::BareCarriageReturn)],
+        );
+        check(r"\", "\u{FFFD}", &[(0..1, EscapeError::LoneSlash)]);
+    }
+
+    #[test]
+    fn test_unescape_other_lossy() {
+        assert_eq!(unescape_char_lossy("a"), ('a', vec![]));
+        assert_eq!(
+            unescape_char_lossy("ab"),
+            (REPLACEMENT_CHARACTER, vec![(0..2, EscapeError::MoreThanOneChar)])
+        );
+        assert_eq!(unescape_byte_lossy(r"\xff", b'?'), (0xff, vec![]));
+        assert_eq!(
+            unescape_byte_lossy("ы", b'?'),
+            (b'?', vec![(0..2, EscapeError::NonAsciiCharInByte)])
+        );
+
+        assert_eq!(
+            unescape_byte_str_lossy(r"a\xffыb", b'?'),
+            (b"a\xff?b".to_vec(), vec![(5..7, EscapeError::NonAsciiCharInByte)])
+        );
+        assert_eq!(
+            unescape_c_str_lossy(r"a\0b\xff"),
+            (b"a\xef\xbf\xbdb\xff".to_vec(), vec![(1..3, EscapeError::NulInCStr)])
+        );
+        assert_eq!(
+            unescape_raw_str_lossy("a\rb\\q"),
+            ("a\u{FFFD}b\\q".to_string(), vec![(1..2, EscapeError::BareCarriageReturnInRawString)])
+        );
+        assert_eq!(
+            unescape_raw_byte_str_lossy("aыb", 0),
+            (b"a\0b".to_vec(), vec![(1..3, EscapeError::NonAsciiCharInByteString)])
+        );
+        assert_eq!(
+            unescape_raw_c_str_lossy("a\0b"),
+            (b"a\xef\xbf\xbdb".to_vec(), vec![(1..2, EscapeError::NulInCStr)])
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Parsing of numeric literal tokens: base prefixes, digits with underscores,
+//! fractions, exponents and suffixes. Ranges of errors are relative to the
+//! start of the token, like the ones of `unescape_literal`.
+
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+impl Base {
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Binary => 2,
+            Base::Octal => 8,
+            Base::Decimal => 10,
+            Base::Hexadecimal => 16,
+        }
+    }
+}
+
+/// The width of `usize` and `isize` on the target, like the
+/// `target_pointer_width` cfg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerWidth {
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+impl PointerWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            PointerWidth::Bits16 => 16,
+            PointerWidth::Bits32 => 32,
+            PointerWidth::Bits64 => 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntLiteral<'a> {
+    pub value: u128,
+    pub base: Base,
+    pub suffix: &'a str,
+}
+
+/// A value of a float literal, rounded to the type of the suffix, or to
+/// `f64` if there's no suffix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatValue {
+    F32(f32),
+    F64(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatLiteral<'a> {
+    pub value: FloatValue,
+    /// The literal without underscores and the suffix, like `1.5e-3`, which
+    /// is the exact decimal value.
+    pub digits: String,
+    pub suffix: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// The token doesn't start with a digit.
+    NotANumber,
+    /// An integer literal passed where a float literal is expected.
+    NotAFloat,
+    /// No digits after the base prefix, only underscores, if any.
+    EmptyDigits,
+    /// A digit which is too large for the base.
+    InvalidDigit(Base),
+    /// No digits after `e`, like in `1e` or `1e+_`.
+    EmptyExponent,
+    /// A fraction, an exponent or a float suffix in a literal which is not
+    /// decimal, like `0x1.0` or `0b1f32`.
+    NonDecimalFloat(Base),
+    InvalidSuffix,
+    /// The value doesn't fit into the type of the suffix, or into `u128` or
+    /// `f64` if there's no suffix.
+    Overflow,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberError::NotANumber => f.write_str("expected a number literal"),
+            NumberError::NotAFloat => f.write_str("expected a float literal"),
+            NumberError::EmptyDigits => f.write_str("no valid digits found for number"),
+            NumberError::InvalidDigit(base) => {
+                write!(f, "invalid digit for a base {} literal", base.radix())
+            }
+            NumberError::EmptyExponent => f.write_str("expected at least one digit in exponent"),
+            NumberError::NonDecimalFloat(base) => {
+                let base = match base {
+                    Base::Binary => "binary",
+                    Base::Octal => "octal",
+                    Base::Decimal => "decimal",
+                    Base::Hexadecimal => "hexadecimal",
+                };
+                write!(f, "{} float literal is not supported", base)
+            }
+            NumberError::InvalidSuffix => f.write_str("invalid suffix for number literal"),
+            NumberError::Overflow => f.write_str("literal is out of range for its type"),
+        }
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+/// Takes a text of a whole integer literal token, like `0x_FF_u8`, and
+/// returns its value or all errors in it.
+///
+/// As the minimum value of a signed type is written as a negation of a
+/// literal, signed suffixes allow values up to the magnitude of the minimum,
+/// like `128i8`. `usize` and `isize` have the given `pointer_width`.
+pub fn parse_int_literal(
+    token_text: &str,
+    pointer_width: PointerWidth,
+) -> Result<IntLiteral<'_>, Vec<(Range<usize>, NumberError)>> {
+    let whole = 0..token_text.len();
+    let (base, digits) =
+        lex_int(token_text).ok_or_else(|| vec![(whole.clone(), NumberError::NotANumber)])?;
+    let suffix = &token_text[digits.end..];
+
+    let mut errors = Vec::new();
+    let mut value = Some(0u128);
+    let mut n_digits = 0;
+    for (i, b) in token_text[digits.clone()].bytes().enumerate() {
+        if b == b'_' {
+            continue;
+        }
+        n_digits += 1;
+        let digit = (b as char).to_digit(16).expect("only hex digits are lexed");
+        if digit >= base.radix() {
+            let start = digits.start + i;
+            errors.push((start..start + 1, NumberError::InvalidDigit(base)));
+            continue;
+        }
+        value = value
+            .and_then(|value| value.checked_mul(base.radix() as u128))
+            .and_then(|value| value.checked_add(digit as u128));
+    }
+    if n_digits == 0 {
+        errors.push((0..digits.end, NumberError::EmptyDigits));
+    }
+
+    let max = int_suffix_max(suffix, pointer_width);
+    if max.is_none() {
+        errors.push((digits.end..token_text.len(), NumberError::InvalidSuffix));
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    match value {
+        Some(value) if Some(value) <= max => Ok(IntLiteral { value, base, suffix }),
+        _ => Err(vec![(whole, NumberError::Overflow)]),
+    }
+}
+
+/// Takes a text of a whole float literal token, like `1.5e-3_f32`, and
+/// returns its value or all errors in it. Integer literals with a float
+/// suffix, like `1f32`, are floats too.
+pub fn parse_float_literal(
+    token_text: &str,
+) -> Result<FloatLiteral<'_>, Vec<(Range<usize>, NumberError)>> {
+    let whole = 0..token_text.len();
+    let (base, int_digits) =
+        lex_int(token_text).ok_or_else(|| vec![(whole.clone(), NumberError::NotANumber)])?;
+    let mut end = int_digits.end;
+    if base != Base::Decimal {
+        let rest = &token_text[end..];
+        let err = if rest.starts_with(&['.', 'e', 'E'][..]) || is_float_suffix(rest) {
+            NumberError::NonDecimalFloat(base)
+        } else {
+            NumberError::NotAFloat
+        };
+        return Err(vec![(whole, err)]);
+    }
+
+    let mut errors = Vec::new();
+    let mut is_float = false;
+    // `1..2` is a range, and `1.foo` and `1._0` are field accesses, not
+    // floats.
+    let after_dot = token_text[end..].strip_prefix('.');
+    if let Some(after_dot) = after_dot {
+        if !after_dot.starts_with(|c: char| c == '.' || c == '_' || c.is_alphabetic()) {
+            is_float = true;
+            end += 1 + dec_digits_len(after_dot);
+        }
+    }
+    if token_text[end..].starts_with(&['e', 'E'][..]) {
+        is_float = true;
+        let exponent_start = end;
+        end += 1;
+        if token_text[end..].starts_with(&['+', '-'][..]) {
+            end += 1;
+        }
+        let digits = &token_text[end..end + dec_digits_len(&token_text[end..])];
+        end += digits.len();
+        if !digits.bytes().any(|b| b.is_ascii_digit()) {
+            errors.push((exponent_start..end, NumberError::EmptyExponent));
+        }
+    }
+
+    let suffix = &token_text[end..];
+    if is_float_suffix(suffix) {
+        is_float = true;
+    } else if !suffix.is_empty() {
+        errors.push((end..token_text.len(), NumberError::InvalidSuffix));
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    if !is_float {
+        return Err(vec![(whole, NumberError::NotAFloat)]);
+    }
+
+    let digits = token_text[..end].replace('_', "");
+    let value = if suffix == "f32" {
+        let value: f32 = digits.parse().expect("the syntax is checked above");
+        if value.is_infinite() {
+            return Err(vec![(whole, NumberError::Overflow)]);
+        }
+        FloatValue::F32(value)
+    } else {
+        let value: f64 = digits.parse().expect("the syntax is checked above");
+        if value.is_infinite() {
+            return Err(vec![(whole, NumberError::Overflow)]);
+        }
+        FloatValue::F64(value)
+    };
+    Ok(FloatLiteral { value, digits, suffix })
+}
+
+fn is_float_suffix(suffix: &str) -> bool {
+    suffix == "f32" || suffix == "f64"
+}
+
+/// Returns the length of the decimal digits and underscores at the start of
+/// `text`.
+fn dec_digits_len(text: &str) -> usize {
+    text.bytes().take_while(|&b| b.is_ascii_digit() || b == b'_').count()
+}
+
+/// Splits a numeric token into its base and the range of digits (with
+/// underscores) after the base prefix. The rest is the suffix. In binary and
+/// octal literals, all decimal digits are lexed, so that too large ones can
+/// be reported.
+fn lex_int(token_text: &str) -> Option<(Base, Range<usize>)> {
+    if !token_text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (base, prefix_len) = match token_text.get(..2) {
+        Some("0b") => (Base::Binary, 2),
+        Some("0o") => (Base::Octal, 2),
+        Some("0x") => (Base::Hexadecimal, 2),
+        _ => (Base::Decimal, 0),
+    };
+    let is_digit = |b: u8| match base {
+        Base::Hexadecimal => b.is_ascii_hexdigit() || b == b'_',
+        Base::Binary | Base::Octal | Base::Decimal => b.is_ascii_digit() || b == b'_',
+    };
+    let n_digits = token_text[prefix_len..].bytes().take_while(|&b| is_digit(b)).count();
+    Some((base, prefix_len..prefix_len + n_digits))
+}
+
+/// Returns the largest value which may be written with an integer `suffix`,
+/// or `None` if the suffix is not an integer one.
+pub(crate) fn int_suffix_max(suffix: &str, pointer_width: PointerWidth) -> Option<u128> {
+    let bits = pointer_width.bits();
+    let max = match suffix {
+        "" | "u128" => u128::MAX,
+        "u8" => u8::MAX as u128,
+        "u16" => u16::MAX as u128,
+        "u32" => u32::MAX as u128,
+        "u64" => u64::MAX as u128,
+        "usize" => (1 << bits) - 1,
+        "i8" => i8::MAX as u128 + 1,
+        "i16" => i16::MAX as u128 + 1,
+        "i32" => i32::MAX as u128 + 1,
+        "i64" => i64::MAX as u128 + 1,
+        "i128" => i128::MAX as u128 + 1,
+        "isize" => 1 << (bits - 1),
+        _ => return None,
+    };
+    Some(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int_literal_good() {
+        fn check(token_text: &str, value: u128, base: Base, suffix: &str) {
+            let actual = parse_int_literal(token_text, PointerWidth::Bits64);
+            assert_eq!(actual, Ok(IntLiteral { value, base, suffix }));
+        }
+
+        check("0", 0, Base::Decimal, "");
+        check("1_000usize", 1000, Base::Decimal, "usize");
+        check("0x_FF_u8", 255, Base::Hexadecimal, "u8");
+        check("0xdead_BEEF", 0xdead_beef, Base::Hexadecimal, "");
+        check("0b1010i64", 10, Base::Binary, "i64");
+        check("0o777", 0o777, Base::Octal, "");
+        check("0_u8", 0, Base::Decimal, "u8");
+        check("128i8", 128, Base::Decimal, "i8");
+        check("340282366920938463463374607431768211455", u128::MAX, Base::Decimal, "");
+    }
+
+    #[test]
+    fn test_parse_int_literal_bad() {
+        fn check(token_text: &str, expected: &[(Range<usize>, NumberError)]) {
+            let actual = parse_int_literal(token_text, PointerWidth::Bits64);
+            assert_eq!(actual.as_ref().map_err(|it| it.as_slice()), Err(expected));
+        }
+
+        check("x1", &[(0..2, NumberError::NotANumber)]);
+        check("0x", &[(0..2, NumberError::EmptyDigits)]);
+        check("0x__u8", &[(0..4, NumberError::EmptyDigits)]);
+        check("0b1021", &[(4..5, NumberError::InvalidDigit(Base::Binary))]);
+        check(
+            "0o78_9",
+            &[
+                (3..4, NumberError::InvalidDigit(Base::Octal)),
+                (5..6, NumberError::InvalidDigit(Base::Octal)),
+            ],
+        );
+        check("1foo", &[(1..4, NumberError::InvalidSuffix)]);
+        check("1.0", &[(1..3, NumberError::InvalidSuffix)]);
+        check(
+            "0b2_u9",
+            &[(2..3, NumberError::InvalidDigit(Base::Binary)), (4..6, NumberError::InvalidSuffix)],
+        );
+        check("256u8", &[(0..5, NumberError::Overflow)]);
+        check("129i8", &[(0..5, NumberError::Overflow)]);
+        check("340282366920938463463374607431768211456", &[(0..39, NumberError::Overflow)]);
+    }
+
+    #[test]
+    fn test_parse_int_literal_pointer_width() {
+        fn check(token_text: &str, pointer_width: PointerWidth, is_ok: bool) {
+            let actual = parse_int_literal(token_text, pointer_width);
+            assert_eq!(actual.is_ok(), is_ok, "{} with {:?}", token_text, pointer_width);
+        }
+
+        check("65535usize", PointerWidth::Bits16, true);
+        check("65536usize", PointerWidth::Bits16, false);
+        check("65536usize", PointerWidth::Bits32, true);
+        check("32768isize", PointerWidth::Bits16, true);
+        check("32769isize", PointerWidth::Bits16, false);
+        check("4294967296usize", PointerWidth::Bits32, false);
+        check("2147483649isize", PointerWidth::Bits32, false);
+        check("18446744073709551615usize", PointerWidth::Bits64, true);
+        check("18446744073709551616usize", PointerWidth::Bits64, false);
+        check("9223372036854775808isize", PointerWidth::Bits64, true);
+        check("9223372036854775809isize", PointerWidth::Bits64, false);
+    }
+
+    #[test]
+    fn test_parse_float_literal_good() {
+        fn check(token_text: &str, value: FloatValue, digits: &str, suffix: &str) {
+            let actual = parse_float_literal(token_text);
+            let expected = FloatLiteral { value, digits: digits.to_string(), suffix };
+            assert_eq!(actual, Ok(expected));
+        }
+
+        check("1.5e-3_f32", FloatValue::F32(1.5e-3), "1.5e-3", "f32");
+        check("2.", FloatValue::F64(2.0), "2.", "");
+        check("1e10", FloatValue::F64(1e10), "1e10", "");
+        check("1_f64", FloatValue::F64(1.0), "1", "f64");
+        check("1_0.2_5E+_1_", FloatValue::F64(102.5), "10.25E+1", "");
+        check("0.1f32", FloatValue::F32(0.1), "0.1", "f32");
+        check("0.1", FloatValue::F64(0.1), "0.1", "");
+        check("1e-400", FloatValue::F64(0.0), "1e-400", "");
+        check(
+            "3.14159265358979323846264338327950288",
+            FloatValue::F64(std::f64::consts::PI),
+            "3.14159265358979323846264338327950288",
+            "",
+        );
+    }
+
+    #[test]
+    fn test_parse_float_literal_bad() {
+        fn check(token_text: &str, expected: &[(Range<usize>, NumberError)]) {
+            let actual = parse_float_literal(token_text);
+            assert_eq!(actual.as_ref().map_err(|it| it.as_slice()), Err(expected));
+        }
+
+        check(".5", &[(0..2, NumberError::NotANumber)]);
+        check("1", &[(0..1, NumberError::NotAFloat)]);
+        check("1u8", &[(1..3, NumberError::InvalidSuffix)]);
+        check("1e", &[(1..2, NumberError::EmptyExponent)]);
+        check("1.0e+_f32", &[(3..6, NumberError::EmptyExponent)]);
+        check("0x1.0", &[(0..5, NumberError::NonDecimalFloat(Base::Hexadecimal))]);
+        check("0b1f32", &[(0..6, NumberError::NonDecimalFloat(Base::Binary))]);
+        check("0o7e3", &[(0..5, NumberError::NonDecimalFloat(Base::Octal))]);
+        check("0x1f32", &[(0..6, NumberError::NotAFloat)]);
+        check("1.foo", &[(1..5, NumberError::InvalidSuffix)]);
+        check("1._5", &[(1..4, NumberError::InvalidSuffix)]);
+        check("1.5.3", &[(3..5, NumberError::InvalidSuffix)]);
+        check("1e39f32", &[(0..7, NumberError::Overflow)]);
+        check("1e309", &[(0..5, NumberError::Overflow)]);
+    }
+
+    #[test]
+    fn test_number_error_display() {
+        assert_eq!(
+            NumberError::InvalidDigit(Base::Octal).to_string(),
+            "invalid digit for a base 8 literal"
+        );
+        assert_eq!(NumberError::EmptyDigits.to_string(), "no valid digits found for number");
+        assert_eq!(
+            NumberError::NonDecimalFloat(Base::Hexadecimal).to_string(),
+            "hexadecimal float literal is not supported"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Delimiters of raw string literals: the `r`, `br` or `cr` prefix, the
+//! hashes, and the quote followed by the same number of hashes which
+//! terminates the literal.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{LiteralError, LiteralKind, Mode};
+
+/// The compiler doesn't accept raw strings delimited with more hashes.
+pub const MAX_RAW_STR_HASHES: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawStrError {
+    /// A character other than `#` or `"` after the prefix, like in `r#x`.
+    InvalidStarter { bad_char: char },
+    /// No quote followed by `expected` hashes. If there is a quote followed
+    /// by fewer hashes, `found` is the largest number of them, and
+    /// `possible_terminator_offset` is the offset of that quote.
+    NoTerminator { expected: usize, found: usize, possible_terminator_offset: Option<usize> },
+    /// More than `MAX_RAW_STR_HASHES` hashes.
+    TooManyDelimiters { found: usize },
+}
+
+impl fmt::Display for RawStrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RawStrError::InvalidStarter { bad_char } => write!(
+                f,
+                "found invalid character; only `#` is allowed in raw string delimitation: {}",
+                bad_char.escape_debug()
+            ),
+            RawStrError::NoTerminator { expected, found, .. } if found > 0 => write!(
+                f,
+                "unterminated raw string: found {} {}, expected {}",
+                found,
+                if found == 1 { "hash" } else { "hashes" },
+                expected
+            ),
+            RawStrError::NoTerminator { expected, .. } => {
+                write!(f, "unterminated raw string: expected `\"{}`", "#".repeat(expected))
+            }
+            RawStrError::TooManyDelimiters { found } => write!(
+                f,
+                "too many `#` symbols: raw strings may be delimited by up to {} `#` symbols, \
+                 but found {}",
+                MAX_RAW_STR_HASHES, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RawStrError {}
+
+/// Takes a text of a whole raw string literal token (with prefix, hashes,
+/// quotes and suffix), and returns its kind and the range of its contents.
+/// Errors are reported at the bad character for `InvalidStarter`, at the
+/// opening delimiter for `NoTerminator`, and at the hashes for
+/// `TooManyDelimiters`.
+pub fn lex_raw_str(
+    token_text: &str,
+) -> Result<(LiteralKind, Range<usize>), (Range<usize>, LiteralError)> {
+    let (prefix_len, kind): (usize, fn(usize) -> LiteralKind) = if token_text.starts_with("br") {
+        (2, |n_hashes| LiteralKind::RawByteStr { n_hashes })
+    } else if token_text.starts_with("cr") {
+        (2, |n_hashes| LiteralKind::RawCStr { n_hashes })
+    } else if token_text.starts_with('r') {
+        (1, |n_hashes| LiteralKind::RawStr { n_hashes })
+    } else {
+        return Err((0..token_text.len(), LiteralError::NotALiteral));
+    };
+    let err = |range: Range<usize>, err: RawStrError| Err((range, LiteralError::RawStr(err)));
+
+    let n_hashes = token_text[prefix_len..].bytes().take_while(|&b| b == b'#').count();
+    let hashes_end = prefix_len + n_hashes;
+    let start = hashes_end + 1;
+    if n_hashes > MAX_RAW_STR_HASHES {
+        return err(prefix_len..hashes_end, RawStrError::TooManyDelimiters { found: n_hashes });
+    }
+    match token_text[hashes_end..].chars().next() {
+        Some('"') => (),
+        Some(bad_char) => {
+            return err(
+                hashes_end..hashes_end + bad_char.len_utf8(),
+                RawStrError::InvalidStarter { bad_char },
+            );
+        }
+        None => {
+            let no_terminator = RawStrError::NoTerminator {
+                expected: n_hashes,
+                found: 0,
+                possible_terminator_offset: None,
+            };
+            return err(0..hashes_end, no_terminator);
+        }
+    }
+
+    let mut found = 0;
+    let mut possible_terminator_offset = None;
+    for (i, _) in token_text[start..].match_indices('"') {
+        let quote = start + i;
+        let n_closing =
+            token_text[quote + 1..].bytes().take(n_hashes).take_while(|&b| b == b'#').count();
+        if n_closing == n_hashes {
+            return Ok((kind(n_hashes), start..quote));
+        }
+        if n_closing > found || possible_terminator_offset.is_none() {
+            found = n_closing;
+            possible_terminator_offset = Some(quote);
+        }
+    }
+    err(
+        0..start,
+        RawStrError::NoTerminator { expected: n_hashes, found, possible_terminator_offset },
+    )
+}
+
+/// Why a value can't be written as a raw literal. Offsets are relative to
+/// the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormError {
+    /// Chars and bytes have no raw form.
+    NotAString(Mode),
+    /// A `\r`. Raw strings reject a bare CR and turn CRLF into LF, so there
+    /// is no way to write one.
+    CarriageReturn { offset: usize },
+    /// A non-ASCII byte in a raw byte string.
+    NonAscii { offset: usize },
+    /// Bytes which are not UTF-8 in a raw string or a raw C string.
+    InvalidUtf8 { offset: usize },
+    /// A NUL in a raw C string.
+    Nul { offset: usize },
+    /// A quote followed by `MAX_RAW_STR_HASHES` hashes.
+    TooManyHashes,
+}
+
+impl fmt::Display for RawFormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawFormError::NotAString(_) => f.write_str("only strings have a raw form"),
+            RawFormError::CarriageReturn { .. } => f.write_str("raw strings can't contain CR"),
+            RawFormError::NonAscii { .. } => {
+                f.write_str("raw byte strings can't contain non-ASCII bytes")
+            }
+            RawFormError::InvalidUtf8 { .. } => f.write_str("raw strings must be valid UTF-8"),
+            RawFormError::Nul { .. } => f.write_str("raw C strings can't contain NUL"),
+            RawFormError::TooManyHashes => {
+                write!(f, "raw string would need more than {} `#` symbols", MAX_RAW_STR_HASHES)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RawFormError {}
+
+/// Returns the smallest number of hashes which delimit `text` as a raw
+/// literal of `mode`, so that `unescape_raw_str` and friends give back
+/// `text` exactly.
+pub fn raw_str_hashes(text: &str, mode: Mode) -> Result<usize, RawFormError> {
+    raw_byte_str_hashes(text.as_bytes(), mode)
+}
+
+/// Like `raw_str_hashes`, but for a value which may not be UTF-8.
+pub fn raw_byte_str_hashes(bytes: &[u8], mode: Mode) -> Result<usize, RawFormError> {
+    match mode {
+        Mode::Char | Mode::Byte => return Err(RawFormError::NotAString(mode)),
+        Mode::ByteStr => {
+            if let Some(offset) = bytes.iter().position(|b| !b.is_ascii()) {
+                return Err(RawFormError::NonAscii { offset });
+            }
+        }
+        Mode::Str | Mode::CStr => {
+            if let Err(err) = std::str::from_utf8(bytes) {
+                return Err(RawFormError::InvalidUtf8 { offset: err.valid_up_to() });
+            }
+        }
+    }
+    if let Some(offset) = bytes.iter().position(|&b| b == b'\r') {
+        return Err(RawFormError::CarriageReturn { offset });
+    }
+    if mode == Mode::CStr {
+        if let Some(offset) = bytes.iter().position(|&b| b == b'\0') {
+            return Err(RawFormError::Nul { offset });
+        }
+    }
+
+    // A quote followed by `n` hashes terminates literals with up to `n` of
+    // them, so one more than the longest such run is needed.
+    let mut n_hashes = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'"' {
+            let run = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+            n_hashes = n_hashes.max(run + 1);
+        }
+    }
+    if n_hashes > MAX_RAW_STR_HASHES {
+        return Err(RawFormError::TooManyHashes);
+    }
+    Ok(n_hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lex_raw_str() {
+        fn check(
+            token_text: &str,
+            expected: Result<(LiteralKind, Range<usize>), (Range<usize>, RawStrError)>,
+        ) {
+            let actual = lex_raw_str(token_text);
+            let expected = expected.map_err(|(range, err)| (range, LiteralError::RawStr(err)));
+            assert_eq!(actual, expected);
+        }
+
+        check(r#"r"a""#, Ok((LiteralKind::RawStr { n_hashes: 0 }, 2..3)));
+        check(r###"br##"a"#b"##"###, Ok((LiteralKind::RawByteStr { n_hashes: 2 }, 5..9)));
+        check(r##"cr#"x"#suffix"##, Ok((LiteralKind::RawCStr { n_hashes: 1 }, 4..5)));
+        check("r#x", Err((2..3, RawStrError::InvalidStarter { bad_char: 'x' })));
+        check("br#ы", Err((3..5, RawStrError::InvalidStarter { bad_char: 'ы' })));
+        check(
+            "r##",
+            Err((
+                0..3,
+                RawStrError::NoTerminator {
+                    expected: 2,
+                    found: 0,
+                    possible_terminator_offset: None,
+                },
+            )),
+        );
+        check(
+            r####"r###"a"#b"##c"####,
+            Err((
+                0..5,
+                RawStrError::NoTerminator {
+                    expected: 3,
+                    found: 2,
+                    possible_terminator_offset: Some(9),
+                },
+            )),
+        );
+        check(
+            r###"r#"a""###,
+            Err((
+                0..3,
+                RawStrError::NoTerminator {
+                    expected: 1,
+                    found: 0,
+                    possible_terminator_offset: Some(4),
+                },
+            )),
+        );
+
+        let ok = format!("r{0}\"a\"{0}", "#".repeat(255));
+        assert_eq!(lex_raw_str(&ok), Ok((LiteralKind::RawStr { n_hashes: 255 }, 257..258)));
+        let too_many = format!("r{0}\"a\"{0}", "#".repeat(256));
+        check(&too_many, Err((1..257, RawStrError::TooManyDelimiters { found: 256 })));
+        let no_quote = format!("r{}", "#".repeat(300));
+        check(&no_quote, Err((1..301, RawStrError::TooManyDelimiters { found: 300 })));
+        let bad_starter = format!("br{}x", "#".repeat(300));
+        check(&bad_starter, Err((2..302, RawStrError::TooManyDelimiters { found: 300 })));
+        assert_eq!(lex_raw_str("'a'"), Err((0..3, LiteralError::NotALiteral)));
+    }
+
+    #[test]
+    fn test_raw_str_error_display() {
+        let err = RawStrError::NoTerminator {
+            expected: 3,
+            found: 2,
+            possible_terminator_offset: Some(9),
+        };
+        assert_eq!(err.to_string(), "unterminated raw string: found 2 hashes, expected 3");
+        let err =
+            RawStrError::NoTerminator { expected: 1, found: 0, possible_terminator_offset: None };
+        assert_eq!(err.to_string(), "unterminated raw string: expected `\"#`");
+        assert_eq!(
+            RawStrError::InvalidStarter { bad_char: 'x' }.to_string(),
+            "found invalid character; only `#` is allowed in raw string delimitation: x"
+        );
+    }
+
+    #[test]
+    fn test_raw_str_hashes() {
+        fn check(text: &str, mode: Mode, expected: usize) {
+            assert_eq!(raw_str_hashes(text, mode), Ok(expected));
+
+            // The literal made with these hashes unescapes back to the text.
+            let prefix = match mode {
+                Mode::Str => "r",
+                Mode::ByteStr => "br",
+                _ => "cr",
+            };
+            let hashes = "#".repeat(expected);
+            let token_text = format!("{}{}\"{}\"{}", prefix, hashes, text, hashes);
+            let (_, range) = lex_raw_str(&token_text).unwrap();
+            let literal_text = &token_text[range];
+            let mut unescaped = String::new();
+            let mut errors = Vec::new();
+            crate::unescape_raw_str(literal_text, &mut |range, c| match c {
+                Ok(c) => unescaped.push(c),
+                Err(err) => errors.push((range, err)),
+            });
+            assert_eq!(errors, []);
+            assert_eq!(unescaped, text);
+
+            // One hash less is not enough.
+            if let Some(fewer) = expected.checked_sub(1) {
+                let hashes = "#".repeat(fewer);
+                let token_text = format!("{}{}\"{}\"{}", prefix, hashes, text, hashes);
+                let contents = lex_raw_str(&token_text).map(|(_, range)| &token_text[range]);
+                assert_ne!(contents, Ok(text));
+            }
+        }
+
+        check("", Mode::Str, 0);
+        check("hello\nworld", Mode::Str, 0);
+        check(r"\n", Mode::Str, 0);
+        check("#", Mode::Str, 0);
+        check("\"", Mode::Str, 1);
+        check("a\"b", Mode::ByteStr, 1);
+        check("\"#", Mode::Str, 2);
+        check("\"##\"#", Mode::CStr, 3);
+        check("ы\"", Mode::CStr, 1);
+        check(&format!("\"{}", "#".repeat(254)), Mode::Str, 255);
+    }
+
+    #[test]
+    fn test_raw_str_hashes_errors() {
+        fn check(bytes: &[u8], mode: Mode, expected: RawFormError) {
+            assert_eq!(raw_byte_str_hashes(bytes, mode), Err(expected));
+        }
+
+        check(b"a", Mode::Char, RawFormError::NotAString(Mode::Char));
+        check(b"a", Mode::Byte, RawFormError::NotAString(Mode::Byte));
+        check(b"a\rb", Mode::Str, RawFormError::CarriageReturn { offset: 1 });
+        check(b"\r\n", Mode::ByteStr, RawFormError::CarriageReturn { offset: 0 });
+        check("aы".as_bytes(), Mode::ByteStr, RawFormError::NonAscii { offset: 1 });
+        check(b"ab\xff", Mode::Str, RawFormError::InvalidUtf8 { offset: 2 });
+        check(b"ab\xff", Mode::CStr, RawFormError::InvalidUtf8 { offset: 2 });
+        check(b"a\0", Mode::CStr, RawFormError::Nul { offset: 1 });
+        assert_eq!(raw_byte_str_hashes(b"a\0", Mode::Str), Ok(0));
+        let too_many = format!("\"{}", "#".repeat(255));
+        assert_eq!(raw_str_hashes(&too_many, Mode::Str), Err(RawFormError::TooManyHashes));
+    }
+}
@@ -0,0 +1,188 @@
+//! Unescaping of char and byte literals which doesn't stop at the first
+//! error, but reports all of them together with a best-guess value.
+
+use crate::{EscapeError, Mode, SpannedError, byte_from_char, scan_escape_with};
+
+/// A best-guess value of a literal with all errors found in it. If there are
+/// no errors, the value is the same as the one returned by `unescape_char`
+/// or `unescape_byte`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered<T> {
+    pub value: Option<T>,
+    pub errors: Vec<SpannedError>,
+}
+
+/// Like `unescape_char`, but recovers from errors inside the escape sequence
+/// and returns all of them.
+pub fn unescape_char_recovering(literal_text: &str) -> Recovered<char> {
+    unescape_char_or_byte_recovering(literal_text, Mode::Char)
+}
+
+/// Like `unescape_byte`, but recovers from errors inside the escape sequence
+/// and returns all of them.
+pub fn unescape_byte_recovering(literal_text: &str) -> Recovered<u8> {
+    let Recovered { value, errors } = unescape_char_or_byte_recovering(literal_text, Mode::Byte);
+    let value = value.filter(|&c| c as u32 <= u8::MAX as u32).map(byte_from_char);
+    Recovered { value, errors }
+}
+
+fn unescape_char_or_byte_recovering(literal_text: &str, mode: Mode) -> Recovered<char> {
+    let mut chars = literal_text.chars();
+    let first_char = match chars.next() {
+        Some(c) => c,
+        None => {
+            let error = EscapeError::ZeroChars;
+            let err = SpannedError { range: 0..0, primary_range: None, error };
+            return Recovered { value: None, errors: vec![err] };
+        }
+    };
+
+    // Each error gets the range `unescape_char` would report if the error
+    // was the first one.
+    let mut errors = Vec::new();
+    let value = scan_escape_with(first_char, &mut chars, mode, &mut |error, rest_len| {
+        let range = 0..literal_text.len() - rest_len;
+        let primary_range = error.primary_range(literal_text, range.clone(), mode);
+        errors.push(SpannedError { range, primary_range, error });
+        true
+    });
+
+    let escape_end = literal_text.len() - chars.as_str().len();
+    if escape_end < literal_text.len() {
+        errors.push(SpannedError {
+            range: 0..literal_text.len(),
+            primary_range: Some(escape_end..literal_text.len()),
+            error: EscapeError::MoreThanOneChar,
+        });
+    }
+    Recovered { value, errors }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Range;
+
+    use crate::{unescape_byte, unescape_char};
+
+    #[test]
+    fn test_unescape_char_recovering() {
+        fn check(
+            literal_text: &str,
+            value: Option<char>,
+            expected: &[(EscapeError, Range<usize>)],
+        ) {
+            let actual = unescape_char_recovering(literal_text);
+            let errors: Vec<_> = actual
+                .errors
+                .into_iter()
+                .map(|err| (err.error, err.primary_range.unwrap_or(err.range)))
+                .collect();
+            assert_eq!((actual.value, errors.as_slice()), (value, expected));
+        }
+
+        check("a", Some('a'), &[]);
+        check(r"\u{1F63b}", Some('😻'), &[]);
+        check("", None, &[(EscapeError::ZeroChars, 0..0)]);
+        check(r"\", Some('\\'), &[(EscapeError::LoneSlash, 0..1)]);
+        check(
+            r"\qx",
+            Some('q'),
+            &[(EscapeError::InvalidEscape, 1..2), (EscapeError::MoreThanOneChar, 2..3)],
+        );
+        check(
+            r"\u{_zz}x",
+            None,
+            &[
+                (EscapeError::LeadingUnderscoreUnicodeEscape, 3..4),
+                (EscapeError::InvalidCharInUnicodeEscape, 4..5),
+                (EscapeError::InvalidCharInUnicodeEscape, 5..6),
+                (EscapeError::MoreThanOneChar, 7..8),
+            ],
+        );
+        check(r"\u{4z1}", Some('A'), &[(EscapeError::InvalidCharInUnicodeEscape, 4..5)]);
+        check(r"\u{41", Some('A'), &[(EscapeError::UnclosedUnicodeEscape, 0..5)]);
+        check(r"\u41", Some('A'), &[(EscapeError::NoBraceInUnicodeEscape, 2..3)]);
+        check(r"\u{}", None, &[(EscapeError::EmptyUnicodeEscape, 0..4)]);
+        check(r"\u{0000000}", None, &[(EscapeError::OverlongUnicodeEscape, 9..10)]);
+        check(r"\u{D800}", None, &[(EscapeError::LoneSurrogateUnicodeEscape, 3..7)]);
+        check(r"\xt1", None, &[(EscapeError::InvalidCharInHexEscape, 2..3)]);
+        check(
+            r"\xtt",
+            None,
+            &[
+                (EscapeError::InvalidCharInHexEscape, 2..3),
+                (EscapeError::InvalidCharInHexEscape, 3..4),
+            ],
+        );
+        check(r"\x80", Some('\u{80}'), &[(EscapeError::OutOfRangeHexEscape, 2..4)]);
+        check(r"\x4", None, &[(EscapeError::TooShortHexEscape, 0..3)]);
+        check(
+            "\r\n",
+            Some('\r'),
+            &[(EscapeError::BareCarriageReturn, 0..1), (EscapeError::MoreThanOneChar, 1..2)],
+        );
+    }
+
+    #[test]
+    fn test_unescape_byte_recovering() {
+        let actual = unescape_byte_recovering(r"\xff");
+        assert_eq!(actual, Recovered { value: Some(0xff), errors: vec![] });
+
+        let actual = unescape_byte_recovering(r"\u{41}");
+        assert_eq!(actual.value, Some(b'A'));
+        assert_eq!(actual.errors[0].error, EscapeError::UnicodeEscapeInByte);
+
+        let actual = unescape_byte_recovering(r"ыa");
+        assert_eq!(actual.value, None);
+        let errors: Vec<_> = actual.errors.iter().map(|it| it.error).collect();
+        assert_eq!(errors, [EscapeError::NonAsciiCharInByte, EscapeError::MoreThanOneChar]);
+    }
+
+    #[test]
+    fn test_recovering_agrees_with_unescape() {
+        let literals = [
+            "a",
+            "ы",
+            "'",
+            "\n",
+            "\t",
+            r"\n",
+            r"\'",
+            r"\x7f",
+            r"\xff",
+            r"\x0",
+            r"\u{0}",
+            r"\u{41}",
+            r"\u{00_41}",
+            r"\u{1F63b}}",
+            r"\u{FFFFFF}",
+            r"\u{DC00}",
+            r"\u{0x}",
+            r"\u[0123]",
+            r"\v",
+            "spam",
+            r"\x0ff",
+            r"\u{_0000}",
+            r"\u{0000000}",
+            r"\u{1000000}",
+            r"\u{110000}",
+            r"\u{_}",
+            r"\u{}",
+            r"\u{41",
+            r"\u41",
+        ];
+        for &literal_text in literals.iter() {
+            let recovered = unescape_char_recovering(literal_text);
+            match unescape_char(literal_text) {
+                Ok(c) => assert_eq!(recovered, Recovered { value: Some(c), errors: vec![] }),
+                Err(err) => assert_eq!(recovered.errors.first(), Some(&err)),
+            }
+            let recovered = unescape_byte_recovering(literal_text);
+            match unescape_byte(literal_text) {
+                Ok(b) => assert_eq!(recovered, Recovered { value: Some(b), errors: vec![] }),
+                Err(err) => assert_eq!(recovered.errors.first(), Some(&err)),
+            }
+        }
+    }
+}
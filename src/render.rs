@@ -0,0 +1,125 @@
+//! Rendering values as whole literal tokens, choosing between the escaped and
+//! the raw form of strings.
+
+use crate::{
+    EscapeOptions, LiteralValue, Mode, escape_byte_str_with, escape_byte_with, escape_char_with,
+    escape_str_with, raw_str_hashes,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// How the escaped form of a literal is written.
+    pub escape: EscapeOptions,
+    /// Never use raw strings.
+    pub no_raw: bool,
+}
+
+/// Renders `value` as a whole literal token (with prefix and quotes) which
+/// `unescape_literal` turns back into `value`. The kind of the literal
+/// follows the value. Strings are written with escapes or raw, whichever is
+/// shorter, and with escapes on a tie. A raw string is only considered when
+/// the escaped form would escape nothing but backslashes and quotes, so it
+/// never hides characters which the escape options make visible.
+///
+/// Returns `None` for a C string with a NUL, which no literal can express.
+pub fn render_literal(value: &LiteralValue, options: &RenderOptions) -> Option<String> {
+    let escape = &options.escape;
+    let (prefix, escaped, text, mode) = match value {
+        LiteralValue::Char(c) => {
+            return Some(format!("'{}'", escape_char_with(*c, Mode::Char, escape)));
+        }
+        LiteralValue::Byte(b) => {
+            return Some(format!("b'{}'", escape_byte_with(*b, Mode::Byte, escape)));
+        }
+        LiteralValue::Str(text) => {
+            ("", escape_str_with(text, Mode::Str, escape), Some(text.as_str()), Mode::Str)
+        }
+        LiteralValue::ByteStr(bytes) => {
+            let escaped = escape_byte_str_with(bytes, Mode::ByteStr, escape)?;
+            ("b", escaped, std::str::from_utf8(bytes).ok(), Mode::ByteStr)
+        }
+        LiteralValue::CStr(bytes) => {
+            let escaped = escape_byte_str_with(bytes, Mode::CStr, escape)?;
+            ("c", escaped, std::str::from_utf8(bytes).ok(), Mode::CStr)
+        }
+    };
+
+    let plain = format!("{}\"{}\"", prefix, escaped);
+    let raw = match text {
+        Some(text) if !options.no_raw => render_raw(prefix, text, mode, &escaped),
+        _ => None,
+    };
+    match raw {
+        Some(raw) if raw.chars().count() < plain.chars().count() => Some(raw),
+        _ => Some(plain),
+    }
+}
+
+fn render_raw(prefix: &str, text: &str, mode: Mode, escaped: &str) -> Option<String> {
+    let n_hashes = raw_str_hashes(text, mode).ok()?;
+    if escaped != text.replace('\\', r"\\").replace('"', "\\\"") {
+        return None;
+    }
+    let hashes = "#".repeat(n_hashes);
+    Some(format!("{}r{}\"{}\"{}", prefix, hashes, text, hashes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unescape_literal;
+
+    fn check_with(value: LiteralValue, options: RenderOptions, expected: &str) {
+        let actual = render_literal(&value, &options).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(unescape_literal(&actual).unwrap().value, value);
+    }
+
+    #[test]
+    fn test_render_literal() {
+        fn check(value: LiteralValue, expected: &str) {
+            check_with(value, RenderOptions::default(), expected);
+        }
+        let str = |text: &str| LiteralValue::Str(text.to_string());
+
+        check(LiteralValue::Char('a'), "'a'");
+        check(LiteralValue::Char('\''), r"'\''");
+        check(LiteralValue::Byte(0xff), r"b'\xff'");
+
+        check(str("hello"), r#""hello""#);
+        check(str(r#"say "hi""#), r#""say \"hi\"""#);
+        check(str(r"C:\dir\file"), r#"r"C:\dir\file""#);
+        check(str(r#"{"a": "b", "c": 1}"#), r###"r#"{"a": "b", "c": 1}"#"###);
+        check(str("\"#"), r##""\"#""##);
+        check(str("a\\b\x1b"), r#""a\\b\u{1b}""#);
+        check(str("line\n\\"), r#""line\n\\""#);
+
+        check(LiteralValue::ByteStr(b"a\\b\\c".to_vec()), r#"br"a\b\c""#);
+        check(LiteralValue::ByteStr(b"\xff\\\\".to_vec()), r#"b"\xff\\\\""#);
+
+        check(LiteralValue::CStr(br"\\\".to_vec()), r#"cr"\\\""#);
+        let mut bytes = "ы\\\\".as_bytes().to_vec();
+        bytes.push(0xff);
+        check(LiteralValue::CStr(bytes), r#"c"ы\\\\\xff""#);
+        check(LiteralValue::CStr(b"\xd1a".to_vec()), r#"c"\xd1a""#);
+        assert_eq!(render_literal(&LiteralValue::CStr(b"a\0".to_vec()), &Default::default()), None);
+    }
+
+    #[test]
+    fn test_render_literal_options() {
+        let value = LiteralValue::Str(r"C:\dir\file".to_string());
+        let options = RenderOptions { no_raw: true, ..RenderOptions::default() };
+        check_with(value, options, r#""C:\\dir\\file""#);
+
+        // With only the required escapes, newlines don't rule out raw strings.
+        let value = LiteralValue::Str("a\\\nb\\".to_string());
+        let escape = EscapeOptions { only_required: true, ..EscapeOptions::default() };
+        let options = RenderOptions { escape, ..RenderOptions::default() };
+        check_with(value, options, "r\"a\\\nb\\\"");
+
+        let value = LiteralValue::Str("ы\\\\".to_string());
+        let escape = EscapeOptions { escape_non_ascii: true, ..EscapeOptions::default() };
+        let options = RenderOptions { escape, ..RenderOptions::default() };
+        check_with(value, options, r#""\u{44b}\\\\""#);
+    }
+}
@@ -0,0 +1,267 @@
+//! A parse of a literal into segments: runs of plain text, escapes of various
+//! kinds and line continuations, with ranges of their parts. Values and
+//! errors come from the same `scan_escape` which is used for unescaping.
+
+use std::ops::Range;
+use std::str::Chars;
+
+use crate::{EscapeError, Mode, find_special_byte, scan_escape, skip_ascii_whitespace};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub range: Range<usize>,
+    pub kind: SegmentKind,
+}
+
+/// Values of escapes are chars. In byte modes, and for `\xNN` escapes in C
+/// strings, a char is a byte value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// A run of characters which stand for themselves.
+    Text,
+    /// A single character which doesn't stand for itself: a CRLF, which
+    /// means `\n`, or a character which is not allowed unescaped.
+    Char(Result<char, EscapeError>),
+    /// `\n`, `\"`, `\0` and the like, or an invalid escape such as `\q`.
+    SimpleEscape(Result<char, EscapeError>),
+    /// `\xNN`.
+    HexEscape { digits: Range<usize>, value: Result<char, EscapeError> },
+    /// `\u{NNNN}`. Ranges of braces are absent if the braces are missing.
+    UnicodeEscape {
+        open_brace: Option<Range<usize>>,
+        digits: Range<usize>,
+        underscores: Vec<Range<usize>>,
+        close_brace: Option<Range<usize>>,
+        value: Result<char, EscapeError>,
+    },
+    /// A backslash followed by a newline, and the whitespace it skips.
+    LineContinuation { whitespace: Range<usize> },
+}
+
+/// Splits a contents of a literal (without quotes) into segments. Errors
+/// which concern the literal as a whole, like `EscapeError::ZeroChars` or
+/// `EscapeError::MoreThanOneChar`, are not reported.
+pub fn segments(literal_text: &str, mode: Mode) -> Vec<Segment> {
+    Segments::new(literal_text, mode).collect()
+}
+
+/// An iterator version of `segments`.
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    literal_text: &'a str,
+    chars: Chars<'a>,
+    mode: Mode,
+}
+
+impl<'a> Segments<'a> {
+    pub fn new(literal_text: &'a str, mode: Mode) -> Segments<'a> {
+        Segments { literal_text, chars: literal_text.chars(), mode }
+    }
+
+    fn offset(&self) -> usize {
+        self.literal_text.len() - self.chars.as_str().len()
+    }
+}
+
+impl Iterator for Segments<'_> {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Segment> {
+        let start = self.offset();
+        let rest = self.chars.as_str();
+        let run_len = if self.mode.in_double_quotes() {
+            find_special_byte(rest.as_bytes(), self.mode)
+        } else {
+            0
+        };
+        if run_len > 0 {
+            self.chars = rest[run_len..].chars();
+            return Some(Segment { range: start..start + run_len, kind: SegmentKind::Text });
+        }
+
+        let first_char = self.chars.next()?;
+        let after_first = self.chars.as_str();
+        let kind = match first_char {
+            '\\' if self.mode.in_double_quotes()
+                && (after_first.starts_with('\n') || after_first.starts_with("\r\n")) =>
+            {
+                skip_ascii_whitespace(&mut self.chars);
+                SegmentKind::LineContinuation { whitespace: start + 1..self.offset() }
+            }
+            '\\' => {
+                let value = scan_escape(first_char, &mut self.chars, self.mode);
+                let value = check_nul(value, self.mode);
+                let range = start..self.offset();
+                escape_kind(&self.literal_text[range.clone()], range.start, value)
+            }
+            '\r' if self.mode.in_double_quotes() && after_first.starts_with('\n') => {
+                self.chars.next();
+                SegmentKind::Char(Ok('\n'))
+            }
+            _ => match check_nul(scan_escape(first_char, &mut self.chars, self.mode), self.mode) {
+                Ok(_) => SegmentKind::Text,
+                Err(err) => SegmentKind::Char(Err(err)),
+            },
+        };
+        Some(Segment { range: start..self.offset(), kind })
+    }
+}
+
+fn check_nul(value: Result<char, EscapeError>, mode: Mode) -> Result<char, EscapeError> {
+    match value {
+        Ok('\0') if matches!(mode, Mode::CStr) => Err(EscapeError::NulInCStr),
+        _ => value,
+    }
+}
+
+/// Classifies an `escape`, which starts with a backslash and is located at
+/// `offset`, by its second character.
+fn escape_kind(escape: &str, offset: usize, value: Result<char, EscapeError>) -> SegmentKind {
+    let end = offset + escape.len();
+    match escape.get(1..2) {
+        Some("x") => SegmentKind::HexEscape { digits: offset + 2..end, value },
+        Some("u") => {
+            let open_brace = match escape.get(2..3) {
+                Some("{") => Some(offset + 2..offset + 3),
+                _ => None,
+            };
+            let close_brace = if open_brace.is_some() && escape.len() > 3 && escape.ends_with('}') {
+                Some(end - 1..end)
+            } else {
+                None
+            };
+            let digits_start = open_brace.as_ref().map_or(offset + 2, |it| it.end);
+            let digits_end = close_brace.as_ref().map_or(end, |it| it.start);
+            let digits = digits_start..digits_end.max(digits_start);
+            let underscores = escape[digits.start - offset..digits.end - offset]
+                .match_indices('_')
+                .map(|(i, _)| digits.start + i..digits.start + i + 1)
+                .collect();
+            SegmentKind::UnicodeEscape { open_brace, digits, underscores, close_brace, value }
+        }
+        _ => SegmentKind::SimpleEscape(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(range: Range<usize>, kind: SegmentKind) -> Segment {
+        Segment { range, kind }
+    }
+
+    #[test]
+    fn test_str_segments() {
+        fn check(literal_text: &str, expected: &[Segment]) {
+            assert_eq!(segments(literal_text, Mode::Str), expected);
+        }
+
+        check("", &[]);
+        check("hello, мир", &[seg(0..13, SegmentKind::Text)]);
+        check(
+            r#"a\n\"b"#,
+            &[
+                seg(0..1, SegmentKind::Text),
+                seg(1..3, SegmentKind::SimpleEscape(Ok('\n'))),
+                seg(3..5, SegmentKind::SimpleEscape(Ok('"'))),
+                seg(5..6, SegmentKind::Text),
+            ],
+        );
+        check(
+            r"\x41\xff\q\",
+            &[
+                seg(0..4, SegmentKind::HexEscape { digits: 2..4, value: Ok('A') }),
+                seg(
+                    4..8,
+                    SegmentKind::HexEscape {
+                        digits: 6..8,
+                        value: Err(EscapeError::OutOfRangeHexEscape),
+                    },
+                ),
+                seg(8..10, SegmentKind::SimpleEscape(Err(EscapeError::InvalidEscape))),
+                seg(10..11, SegmentKind::SimpleEscape(Err(EscapeError::LoneSlash))),
+            ],
+        );
+        check(
+            r"\u{1_F6_3b}",
+            &[seg(
+                0..11,
+                SegmentKind::UnicodeEscape {
+                    open_brace: Some(2..3),
+                    digits: 3..10,
+                    underscores: vec![4..5, 7..8],
+                    close_brace: Some(10..11),
+                    value: Ok('😻'),
+                },
+            )],
+        );
+        check(
+            r"\u{41",
+            &[seg(
+                0..5,
+                SegmentKind::UnicodeEscape {
+                    open_brace: Some(2..3),
+                    digits: 3..5,
+                    underscores: vec![],
+                    close_brace: None,
+                    value: Err(EscapeError::UnclosedUnicodeEscape),
+                },
+            )],
+        );
+        check(
+            r"\u41",
+            &[
+                seg(
+                    0..3,
+                    SegmentKind::UnicodeEscape {
+                        open_brace: None,
+                        digits: 2..3,
+                        underscores: vec![],
+                        close_brace: None,
+                        value: Err(EscapeError::NoBraceInUnicodeEscape),
+                    },
+                ),
+                seg(3..4, SegmentKind::Text),
+            ],
+        );
+        check(
+            "a\\\n  \t b\r\nc\"\r",
+            &[
+                seg(0..1, SegmentKind::Text),
+                seg(1..7, SegmentKind::LineContinuation { whitespace: 2..7 }),
+                seg(7..8, SegmentKind::Text),
+                seg(8..10, SegmentKind::Char(Ok('\n'))),
+                seg(10..11, SegmentKind::Text),
+                seg(11..12, SegmentKind::Char(Err(EscapeError::EscapeOnlyChar))),
+                seg(12..13, SegmentKind::Char(Err(EscapeError::BareCarriageReturn))),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_other_mode_segments() {
+        assert_eq!(
+            segments("aы", Mode::ByteStr),
+            [
+                seg(0..1, SegmentKind::Text),
+                seg(1..3, SegmentKind::Char(Err(EscapeError::NonAsciiCharInByte))),
+            ]
+        );
+        assert_eq!(
+            segments(r"a\0", Mode::CStr),
+            [
+                seg(0..1, SegmentKind::Text),
+                seg(1..3, SegmentKind::SimpleEscape(Err(EscapeError::NulInCStr))),
+            ]
+        );
+        assert_eq!(
+            segments("\n", Mode::Char),
+            [seg(0..1, SegmentKind::Char(Err(EscapeError::EscapeOnlyChar)))]
+        );
+        assert_eq!(
+            segments(r"a\n", Mode::Char),
+            [seg(0..1, SegmentKind::Text), seg(1..3, SegmentKind::SimpleEscape(Ok('\n')))]
+        );
+    }
+}
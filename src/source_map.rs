@@ -0,0 +1,261 @@
+//! Mapping offsets in an unescaped value back to the literal it came from,
+//! and the other way around.
+
+use std::ops::Range;
+
+use crate::{Chunk, EscapeErrors, UnescapeByteStrChunks, UnescapeStrChunks};
+
+/// Maps between byte offsets of an unescaped value and of the contents of
+/// its literal (without quotes). Lookups are binary searches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Pieces of the value in order, as pairs of value and source ranges.
+    /// Pieces with ranges of the same length are runs which map byte to
+    /// byte. The others are escapes, which map as a whole, as an escape is
+    /// always longer than its value.
+    pieces: Vec<(Range<usize>, Range<usize>)>,
+    /// Source ranges of multi-byte chars inside runs, in order, so that
+    /// lookups don't split them.
+    wide_chars: Vec<Range<usize>>,
+    source_len: usize,
+}
+
+impl SourceMap {
+    /// Creates an empty map for a literal of `source_len` bytes.
+    pub fn new(source_len: usize) -> SourceMap {
+        SourceMap { pieces: Vec::new(), wide_chars: Vec::new(), source_len }
+    }
+
+    /// Records that the next `value_len` bytes of the value, a single char
+    /// or byte, come from `source_range`. Works with ranges reported by all
+    /// `unescape_*` functions, which are passed in order.
+    pub fn push(&mut self, source_range: Range<usize>, value_len: usize) {
+        if source_range.len() == value_len && value_len > 1 {
+            self.wide_chars.push(source_range.clone());
+        }
+        self.push_piece(source_range, value_len);
+    }
+
+    /// Records that the next part of the value is `run`, which stands for
+    /// itself at `source_range`, like `Chunk::Run`.
+    pub fn push_run(&mut self, source_range: Range<usize>, run: &str) {
+        let start = source_range.start;
+        let wide_chars = run.char_indices().filter(|(_, c)| c.len_utf8() > 1);
+        self.wide_chars.extend(wide_chars.map(|(i, c)| start + i..start + i + c.len_utf8()));
+        self.push_piece(source_range, run.len());
+    }
+
+    fn push_piece(&mut self, source_range: Range<usize>, value_len: usize) {
+        let value_start = self.pieces.last().map_or(0, |(value, _)| value.end);
+        let value_range = value_start..value_start + value_len;
+        if let Some((last_value, last_source)) = self.pieces.last_mut() {
+            let is_run = |value: &Range<usize>, source: &Range<usize>| value.len() == source.len();
+            if is_run(last_value, last_source)
+                && is_run(&value_range, &source_range)
+                && last_source.end == source_range.start
+            {
+                last_value.end = value_range.end;
+                last_source.end = source_range.end;
+                return;
+            }
+        }
+        self.pieces.push((value_range, source_range));
+    }
+
+    pub fn value_len(&self) -> usize {
+        self.pieces.last().map_or(0, |(value, _)| value.end)
+    }
+
+    /// Returns the range of the literal which produced the byte of the value
+    /// at `value_offset`: the char for characters which stand for themselves,
+    /// and the whole escape otherwise. The end of the value maps to the end
+    /// of the literal.
+    pub fn to_source(&self, value_offset: usize) -> Range<usize> {
+        let idx = self.pieces.partition_point(|(value, _)| value.end <= value_offset);
+        let (value, source) = match self.pieces.get(idx) {
+            Some(piece) => piece,
+            None => return self.source_len..self.source_len,
+        };
+        if value.len() == source.len() {
+            let offset = source.start + (value_offset - value.start);
+            self.wide_char_at(offset).unwrap_or(offset..offset + 1)
+        } else {
+            source.clone()
+        }
+    }
+
+    /// Returns the range of the literal which produced `value_range` of the
+    /// value.
+    pub fn to_source_range(&self, value_range: Range<usize>) -> Range<usize> {
+        let start = self.to_source(value_range.start).start;
+        if value_range.is_empty() {
+            return start..start;
+        }
+        start..self.to_source(value_range.end - 1).end
+    }
+
+    /// Returns the offset in the value which corresponds to `source_offset`
+    /// of the literal. Offsets inside an escape or a multi-byte char map to
+    /// the start of its value, and offsets in skipped text, like line
+    /// continuations, map to the start of the value which follows.
+    pub fn to_value(&self, source_offset: usize) -> usize {
+        let idx = self.pieces.partition_point(|(_, source)| source.start <= source_offset);
+        let (value, source) = match idx.checked_sub(1).and_then(|idx| self.pieces.get(idx)) {
+            Some(piece) => piece,
+            None => return 0,
+        };
+        if source_offset >= source.end {
+            value.end
+        } else if value.len() == source.len() {
+            let source_offset = self.wide_char_at(source_offset).map_or(source_offset, |c| c.start);
+            value.start + (source_offset - source.start)
+        } else {
+            value.start
+        }
+    }
+
+    fn wide_char_at(&self, source_offset: usize) -> Option<Range<usize>> {
+        let idx = self.wide_chars.partition_point(|c| c.end <= source_offset);
+        self.wide_chars.get(idx).filter(|c| c.start <= source_offset).cloned()
+    }
+}
+
+/// Like `unescape_str_cow`, but always produces an owned string together with
+/// its `SourceMap`.
+pub fn unescape_str_mapped(literal_text: &str) -> Result<(String, SourceMap), EscapeErrors> {
+    let mut buf = String::with_capacity(literal_text.len());
+    let mut map = SourceMap::new(literal_text.len());
+    let mut errors = Vec::new();
+    for (range, chunk) in UnescapeStrChunks::new(literal_text) {
+        match chunk {
+            Ok(Chunk::Run(run)) => {
+                buf.push_str(run);
+                map.push_run(range, run);
+            }
+            Ok(Chunk::Unit(c)) => {
+                buf.push(c);
+                map.push(range, c.len_utf8());
+            }
+            Err(err) => errors.push((range, err)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok((buf, map))
+}
+
+/// Like `unescape_byte_str_cow`, but always produces owned bytes together with
+/// their `SourceMap`.
+pub fn unescape_byte_str_mapped(literal_text: &str) -> Result<(Vec<u8>, SourceMap), EscapeErrors> {
+    let mut buf = Vec::with_capacity(literal_text.len());
+    let mut map = SourceMap::new(literal_text.len());
+    let mut errors = Vec::new();
+    for (range, chunk) in UnescapeByteStrChunks::new(literal_text) {
+        match chunk {
+            Ok(Chunk::Run(run)) => {
+                buf.extend_from_slice(run.as_bytes());
+                map.push_run(range, run);
+            }
+            Ok(Chunk::Unit(b)) => {
+                buf.push(b);
+                map.push(range, 1);
+            }
+            Err(err) => errors.push((range, err)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok((buf, map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unescape_str;
+
+    #[test]
+    fn test_to_source() {
+        // value: a \n b c 😻 d
+        let literal_text = "a\\nb\\\n   c\\u{1F63b}d";
+        let (value, map) = unescape_str_mapped(literal_text).unwrap();
+        assert_eq!(value, "a\nbc😻d");
+        assert_eq!(map.value_len(), value.len());
+
+        let actual: Vec<_> =
+            (0..=value.len()).map(|offset| &literal_text[map.to_source(offset)]).collect();
+        assert_eq!(
+            actual,
+            ["a", r"\n", "b", "c", r"\u{1F63b}", r"\u{1F63b}", r"\u{1F63b}", r"\u{1F63b}", "d", ""]
+        );
+
+        let cat = value.find('😻').unwrap();
+        assert_eq!(&literal_text[map.to_source_range(0..3)], "a\\nb");
+        assert_eq!(&literal_text[map.to_source_range(3..cat + 4)], r"c\u{1F63b}");
+        assert_eq!(map.to_source_range(2..2), 3..3);
+    }
+
+    #[test]
+    fn test_to_value() {
+        let literal_text = "a\\nb\\\n   c\\u{1F63b}d";
+        let (value, map) = unescape_str_mapped(literal_text).unwrap();
+        #[rustfmt::skip]
+        let expected = [
+            0,             // a
+            1, 1,          // \n
+            2,             // b
+            3, 3, 3, 3, 3, // line continuation
+            3,             // c
+            4, 4, 4, 4, 4, 4, 4, 4, 4, // \u{1F63b}
+            8,             // d
+            9,
+        ];
+        let actual: Vec<_> = (0..=literal_text.len()).map(|offset| map.to_value(offset)).collect();
+        assert_eq!(actual, expected);
+        assert_eq!(value.len(), 9);
+    }
+
+    #[test]
+    fn test_byte_str_and_callbacks() {
+        let literal_text = r"a\xffbc";
+        let (value, map) = unescape_byte_str_mapped(literal_text).unwrap();
+        assert_eq!(value, b"a\xffbc");
+        assert_eq!(map.to_source(1), 1..5);
+        assert_eq!(map.to_source(3), 6..7);
+        assert_eq!(map.to_value(3), 1);
+        assert_eq!(map.to_value(5), 2);
+
+        assert!(unescape_byte_str_mapped(r"\q").is_err());
+
+        let literal_text = r"ы\tz";
+        let mut map = SourceMap::new(literal_text.len());
+        unescape_str(literal_text, &mut |range, c| map.push(range, c.unwrap().len_utf8()));
+        assert_eq!(map.to_source(1), 0..2);
+        assert_eq!(map.to_source(2), 2..4);
+        assert_eq!(map.to_value(1), 0);
+        assert_eq!(map.to_value(4), 3);
+    }
+
+    #[test]
+    fn test_wide_chars_in_runs() {
+        for &literal_text in [
+            "ыz", "aы😻b", r"ы
+😻",
+        ]
+        .iter()
+        {
+            let (value, map) = unescape_str_mapped(literal_text).unwrap();
+            for offset in 0..value.len() {
+                let range = map.to_source(offset);
+                let source_char = &literal_text[range.clone()];
+                let value_char = &value[map.to_value(range.start)..];
+                assert!(value_char.starts_with(source_char) || source_char.starts_with('\\'));
+            }
+        }
+        let (_, map) = unescape_str_mapped("ыz").unwrap();
+        assert_eq!(map.to_source(1), 0..2);
+        assert_eq!(map.to_source(2), 2..3);
+        assert_eq!(map.to_value(1), 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Checking suffixes of literals. Any literal may have a suffix at the token
+//! level, but only numeric types are meaningful ones, and only on numbers.
+
+use std::fmt;
+
+use crate::Mode;
+use crate::number::{PointerWidth, int_suffix_max};
+
+/// What a suffix is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuffixTarget {
+    /// A char, byte or string literal.
+    Quoted(Mode),
+    Int,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuffixKind {
+    None,
+    /// `u8`, `isize` and the like.
+    Int,
+    /// `f32` or `f64`.
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuffixError {
+    /// Any suffix on a char, byte or string literal.
+    OnQuotedLiteral(Mode),
+    /// `f16` and `f128`, numeric types which are not stable.
+    Reserved,
+    /// Anything else which is not a numeric type, or an integer type on a
+    /// float literal.
+    Invalid,
+}
+
+impl fmt::Display for SuffixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuffixError::OnQuotedLiteral(mode) => {
+                let kind = match mode {
+                    Mode::Char => "char",
+                    Mode::Byte => "byte",
+                    Mode::Str => "string",
+                    Mode::ByteStr => "byte string",
+                    Mode::CStr => "C string",
+                };
+                write!(f, "suffixes on {} literals are invalid", kind)
+            }
+            SuffixError::Reserved => f.write_str("suffix is reserved for an unstable numeric type"),
+            SuffixError::Invalid => f.write_str("invalid suffix for number literal"),
+        }
+    }
+}
+
+impl std::error::Error for SuffixError {}
+
+/// Classifies a `suffix` of a literal. An integer literal with a float
+/// suffix, like `1f32`, is a float literal.
+pub fn check_suffix(suffix: &str, target: SuffixTarget) -> Result<SuffixKind, SuffixError> {
+    if suffix.is_empty() {
+        return Ok(SuffixKind::None);
+    }
+    if let SuffixTarget::Quoted(mode) = target {
+        return Err(SuffixError::OnQuotedLiteral(mode));
+    }
+    match suffix {
+        "f32" | "f64" => return Ok(SuffixKind::Float),
+        "f16" | "f128" => return Err(SuffixError::Reserved),
+        _ => (),
+    }
+    match target {
+        // Whether a suffix is an integer one doesn't depend on the width.
+        SuffixTarget::Int if int_suffix_max(suffix, PointerWidth::Bits64).is_some() => {
+            Ok(SuffixKind::Int)
+        }
+        _ => Err(SuffixError::Invalid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unescape_literal;
+
+    #[test]
+    fn test_check_suffix() {
+        fn check(suffix: &str, target: SuffixTarget, expected: Result<SuffixKind, SuffixError>) {
+            assert_eq!(check_suffix(suffix, target), expected);
+        }
+
+        check("", SuffixTarget::Quoted(Mode::Str), Ok(SuffixKind::None));
+        check("", SuffixTarget::Float, Ok(SuffixKind::None));
+        check("u8", SuffixTarget::Int, Ok(SuffixKind::Int));
+        check("i128", SuffixTarget::Int, Ok(SuffixKind::Int));
+        check("f32", SuffixTarget::Int, Ok(SuffixKind::Float));
+        check("f64", SuffixTarget::Float, Ok(SuffixKind::Float));
+        check("u8", SuffixTarget::Float, Err(SuffixError::Invalid));
+        check("f16", SuffixTarget::Float, Err(SuffixError::Reserved));
+        check("f128", SuffixTarget::Int, Err(SuffixError::Reserved));
+        check("u7", SuffixTarget::Int, Err(SuffixError::Invalid));
+        check(
+            "u8",
+            SuffixTarget::Quoted(Mode::Byte),
+            Err(SuffixError::OnQuotedLiteral(Mode::Byte)),
+        );
+    }
+
+    #[test]
+    fn test_check_literal_suffix() {
+        let literal = unescape_literal(r#""foo"bar"#).unwrap();
+        let err =
+            check_suffix(literal.suffix, SuffixTarget::Quoted(literal.kind.mode())).unwrap_err();
+        assert_eq!(err, SuffixError::OnQuotedLiteral(Mode::Str));
+        assert_eq!(err.to_string(), "suffixes on string literals are invalid");
+        assert_eq!(
+            SuffixError::OnQuotedLiteral(Mode::CStr).to_string(),
+            "suffixes on C string literals are invalid"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Checking literals for errors without producing their values. Runs of
+//! characters which stand for themselves are skipped with
+//! `find_special_byte`, and only the rest is looked at char by char.
+
+use std::ops::Range;
+use std::str::Chars;
+
+use crate::{
+    EscapeError, EscapeErrors, Mode, find_special_byte, scan_escape, skip_ascii_whitespace,
+    unescape_char_or_byte, unescape_simple,
+};
+
+/// Checks a contents of a literal (without quotes) in `mode`, and returns the
+/// first error, if there is any.
+pub fn validate(literal_text: &str, mode: Mode) -> Result<(), (Range<usize>, EscapeError)> {
+    match errors(literal_text, mode, false).next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Like `validate`, but returns all errors.
+pub fn validate_all(literal_text: &str, mode: Mode) -> Result<(), EscapeErrors> {
+    collect_errors(errors(literal_text, mode, false))
+}
+
+/// Checks a contents of a raw string literal (without quotes and hashes) in
+/// `mode`, and returns the first error, if there is any.
+pub fn validate_raw(literal_text: &str, mode: Mode) -> Result<(), (Range<usize>, EscapeError)> {
+    match errors(literal_text, mode, true).next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Like `validate_raw`, but returns all errors.
+pub fn validate_raw_all(literal_text: &str, mode: Mode) -> Result<(), EscapeErrors> {
+    collect_errors(errors(literal_text, mode, true))
+}
+
+fn collect_errors(
+    errors: impl Iterator<Item = (Range<usize>, EscapeError)>,
+) -> Result<(), EscapeErrors> {
+    let errors: EscapeErrors = errors.collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn errors(literal_text: &str, mode: Mode, is_raw: bool) -> Errors<'_> {
+    if mode.in_single_quotes() {
+        assert!(!is_raw, "there are no raw char or byte literals");
+        let err = unescape_char_or_byte(literal_text, mode).err();
+        return Errors::Single(err.map(|err| (err.range, err.error)));
+    }
+    Errors::Str { literal_text, chars: literal_text.chars(), mode, is_raw }
+}
+
+/// Errors of a literal. In double quotes, only the characters which may not
+/// stand for themselves are looked at.
+enum Errors<'a> {
+    Single(Option<(Range<usize>, EscapeError)>),
+    Str { literal_text: &'a str, chars: Chars<'a>, mode: Mode, is_raw: bool },
+}
+
+impl Iterator for Errors<'_> {
+    type Item = (Range<usize>, EscapeError);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (literal_text, chars, mode, is_raw) = match self {
+            Errors::Single(err) => return err.take(),
+            Errors::Str { literal_text, chars, mode, is_raw } => {
+                (*literal_text, chars, *mode, *is_raw)
+            }
+        };
+        let offset = |chars: &Chars<'_>| literal_text.len() - chars.as_str().len();
+
+        loop {
+            let rest = chars.as_str();
+            let skip = if is_raw {
+                find_special_byte(rest.as_bytes(), mode)
+            } else {
+                skip_valid(rest.as_bytes(), mode)
+            };
+            *chars = rest[skip..].chars();
+            let start = offset(chars);
+            let first_char = chars.next()?;
+
+            let res = match first_char {
+                '\r' if chars.as_str().starts_with('\n') => {
+                    chars.next();
+                    continue;
+                }
+                '\r' if is_raw => Err(EscapeError::BareCarriageReturnInRawString),
+                _ if is_raw && mode.is_bytes() && !first_char.is_ascii() => {
+                    Err(EscapeError::NonAsciiCharInByteString)
+                }
+                _ if is_raw => Ok(first_char),
+                '\\' if chars.as_str().starts_with('\n') || chars.as_str().starts_with("\r\n") => {
+                    skip_ascii_whitespace(chars);
+                    continue;
+                }
+                _ => scan_escape(first_char, chars, mode),
+            };
+            let res = match res {
+                Ok('\0') if matches!(mode, Mode::CStr) => Err(EscapeError::NulInCStr),
+                _ => res,
+            };
+            if let Err(err) = res {
+                return Some((start..offset(chars), err));
+            }
+        }
+    }
+}
+
+/// Returns the length of the valid prefix of `bytes` made of runs which
+/// stand for themselves and common escapes. Stops before anything else,
+/// which may or may not be valid.
+fn skip_valid(bytes: &[u8], mode: Mode) -> usize {
+    let mut pos = 0;
+    loop {
+        // Escapes often come in a row, so don't search for the end of a run
+        // which is empty anyway.
+        if bytes.get(pos) != Some(&b'\\') {
+            pos += find_special_byte(&bytes[pos..], mode);
+        }
+        match bytes[pos..] {
+            [b'\\', ref rest @ ..] => match valid_escape_len(rest, mode) {
+                Some(len) => pos += 1 + len,
+                None => return pos,
+            },
+            _ => return pos,
+        }
+    }
+}
+
+/// Returns the length of a valid escape which is common enough to check
+/// without `scan_escape`, like `\n` or `\x41`, given the text after its
+/// backslash.
+fn valid_escape_len(rest: &[u8], mode: Mode) -> Option<usize> {
+    match *rest {
+        [b'x', hi, lo, ..] => {
+            let value = (hi as char).to_digit(16)? * 16 + (lo as char).to_digit(16)?;
+            let is_valid =
+                (value < 0x80 || mode.allows_high_bytes()) && !(value == 0 && mode == Mode::CStr);
+            if is_valid {
+                Some(3)
+            } else {
+                None
+            }
+        }
+        [c, ..] => match unescape_simple(c as char)? {
+            '\0' if mode == Mode::CStr => None,
+            _ => Some(1),
+        },
+        [] => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        UnescapeByteStr, UnescapeCStr, UnescapeRawByteStr, UnescapeRawCStr, UnescapeRawStr,
+        UnescapeStr,
+    };
+
+    fn unescape_errors<T>(
+        units: impl Iterator<Item = (Range<usize>, Result<T, EscapeError>)>,
+    ) -> EscapeErrors {
+        units.filter_map(|(range, res)| res.err().map(|err| (range, err))).collect()
+    }
+
+    #[test]
+    fn test_validate_agrees_with_unescape() {
+        let literals = [
+            "",
+            "hello, world",
+            "a long run of plain text before an escape \\n and after it",
+            "мир",
+            "a\"b",
+            r"\x41\x80\xff\u{0}\u{1F63b}\u{D800}",
+            r"\q\u{_1}\u{}ሴ\",
+            r"\t\x00\x7f\x7g\xG1\n\x4",
+            "a\\\n   b\\\r\n\tc",
+            "a\r\nb\rc",
+            "a\0b",
+            r"\0",
+            "\\\\\\",
+        ];
+        for &literal_text in literals.iter() {
+            let expected = [
+                (Mode::Str, unescape_errors(UnescapeStr::new(literal_text))),
+                (Mode::ByteStr, unescape_errors(UnescapeByteStr::new(literal_text))),
+                (Mode::CStr, unescape_errors(UnescapeCStr::new(literal_text))),
+            ];
+            for (mode, expected) in expected.iter() {
+                let actual = validate_all(literal_text, *mode).err().unwrap_or_default();
+                assert_eq!(&actual, expected, "{:?} in {:?}", literal_text, mode);
+                let actual = validate(literal_text, *mode).err();
+                assert_eq!(actual.as_ref(), expected.first());
+            }
+
+            let expected = [
+                (Mode::Str, unescape_errors(UnescapeRawStr::new(literal_text))),
+                (Mode::ByteStr, unescape_errors(UnescapeRawByteStr::new(literal_text))),
+                (Mode::CStr, unescape_errors(UnescapeRawCStr::new(literal_text))),
+            ];
+            for (mode, expected) in expected.iter() {
+                let actual = validate_raw_all(literal_text, *mode).err().unwrap_or_default();
+                assert_eq!(&actual, expected, "raw {:?} in {:?}", literal_text, mode);
+                let actual = validate_raw(literal_text, *mode).err();
+                assert_eq!(actual.as_ref(), expected.first());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_char() {
+        assert_eq!(validate("a", Mode::Char), Ok(()));
+        assert_eq!(validate(r"\xff", Mode::Byte), Ok(()));
+        assert_eq!(validate(r"\xff", Mode::Char), Err((0..4, EscapeError::OutOfRangeHexEscape)));
+        assert_eq!(validate_all("ab", Mode::Char), Err(vec![(0..2, EscapeError::MoreThanOneChar)]));
+    }
+}
@@ -0,0 +1,120 @@
+//! Things which are allowed in literals, but most likely are not what the
+//! author meant. Unlike `EscapeError`s, these don't affect the value.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{EscapeError, Mode, SegmentKind, Segments};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Nothing in this crate is a lint by itself. Consumers may report
+    /// their own style checks with this severity.
+    Lint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeWarning {
+    /// A line continuation skips over more than one line.
+    MultipleSkippedLines,
+    /// A line continuation is followed by a whitespace character other than
+    /// space, tab, CR or LF, like U+00A0 or form feed, which is not skipped.
+    UnskippedWhitespace(char),
+}
+
+impl fmt::Display for EscapeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapeWarning::MultipleSkippedLines => {
+                f.write_str("multiple lines skipped by escaped newline")
+            }
+            EscapeWarning::UnskippedWhitespace(c) => {
+                write!(f, "whitespace symbol (U+{:04X}) is not skipped", *c as u32)
+            }
+        }
+    }
+}
+
+impl EscapeError {
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+}
+
+impl EscapeWarning {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+/// Returns warnings about line continuations in a contents of a string
+/// literal (without quotes). A range of `MultipleSkippedLines` covers the
+/// skipped whitespace, and a range of `UnskippedWhitespace` covers the
+/// character which is not skipped.
+pub fn line_continuation_warnings(
+    literal_text: &str,
+    mode: Mode,
+) -> Vec<(Range<usize>, EscapeWarning)> {
+    assert!(mode.in_double_quotes());
+    let mut warnings = Vec::new();
+    for segment in Segments::new(literal_text, mode) {
+        let whitespace = match segment.kind {
+            SegmentKind::LineContinuation { whitespace } => whitespace,
+            _ => continue,
+        };
+        if literal_text[whitespace.clone()].matches('\n').count() > 1 {
+            warnings.push((whitespace.clone(), EscapeWarning::MultipleSkippedLines));
+        }
+        if let Some(c) = literal_text[whitespace.end..].chars().next() {
+            if c.is_whitespace() {
+                let range = whitespace.end..whitespace.end + c.len_utf8();
+                warnings.push((range, EscapeWarning::UnskippedWhitespace(c)));
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_continuation_warnings() {
+        fn check(literal_text: &str, expected: &[(Range<usize>, EscapeWarning)]) {
+            assert_eq!(line_continuation_warnings(literal_text, Mode::Str), expected);
+        }
+
+        check("a\\\n   b", &[]);
+        check("a\\\r\n\tb", &[]);
+        check("a\\\n\n  b", &[(2..6, EscapeWarning::MultipleSkippedLines)]);
+        check("a\\\r\n \r\nb", &[(2..7, EscapeWarning::MultipleSkippedLines)]);
+        check("a\\\n \u{a0}b", &[(4..6, EscapeWarning::UnskippedWhitespace('\u{a0}'))]);
+        check(
+            "\\\n\n\u{3000}",
+            &[
+                (1..3, EscapeWarning::MultipleSkippedLines),
+                (3..6, EscapeWarning::UnskippedWhitespace('\u{3000}')),
+            ],
+        );
+        check("a\\\n\x0cb", &[(3..4, EscapeWarning::UnskippedWhitespace('\x0c'))]);
+        check("a\\\\\n\n\u{a0}", &[]);
+        assert_eq!(line_continuation_warnings("\\\n\n", Mode::ByteStr).len(), 1);
+    }
+
+    #[test]
+    fn test_escape_warning_display() {
+        assert_eq!(
+            EscapeWarning::MultipleSkippedLines.to_string(),
+            "multiple lines skipped by escaped newline"
+        );
+        assert_eq!(
+            EscapeWarning::UnskippedWhitespace('\u{a0}').to_string(),
+            "whitespace symbol (U+00A0) is not skipped"
+        );
+        assert_eq!(EscapeWarning::MultipleSkippedLines.severity(), Severity::Warning);
+        assert_eq!(EscapeError::LoneSlash.severity(), Severity::Error);
+    }
+}